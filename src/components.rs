@@ -3,6 +3,61 @@ use std::ops::{Add, AddAssign, Div, Mul, Sub, SubAssign};
 use bevy::prelude::*;
 use bevy_inspector_egui::{prelude::ReflectInspectorOptions, InspectorOptions};
 
+/// Scalar type the simulation state is stored in: `f32` by default, `f64` with
+/// the `f64` feature. Rendering always works on a down-converted `f32` copy.
+#[cfg(not(feature = "f64"))]
+pub type Real = f32;
+#[cfg(feature = "f64")]
+pub type Real = f64;
+
+/// Vector counterpart of [`Real`].
+#[cfg(not(feature = "f64"))]
+pub type RealVec3 = Vec3;
+#[cfg(feature = "f64")]
+pub type RealVec3 = bevy::math::DVec3;
+
+/// Mathematical constants in [`Real`] precision.
+#[cfg(not(feature = "f64"))]
+pub use std::f32::consts;
+#[cfg(feature = "f64")]
+pub use std::f64::consts;
+
+/// Converts a simulation scalar to `f32` for rendering.
+#[allow(clippy::unnecessary_cast)]
+pub fn to_f32(x: Real) -> f32 {
+    x as f32
+}
+
+/// Converts a simulation scalar to `f64` for plotting.
+#[allow(clippy::useless_conversion)]
+pub fn to_f64(x: Real) -> f64 {
+    f64::from(x)
+}
+
+/// Converts an `f32` (from the UI or the wall clock, say) into simulation
+/// precision.
+#[allow(clippy::useless_conversion)]
+pub fn from_f32(x: f32) -> Real {
+    Real::from(x)
+}
+
+/// Converts a simulation vector to a `Vec3` for rendering.
+pub fn to_vec3(v: RealVec3) -> Vec3 {
+    #[cfg(not(feature = "f64"))]
+    return v;
+    #[cfg(feature = "f64")]
+    return v.as_vec3();
+}
+
+/// Converts a world-space `Vec3` (from a `Transform` or the mouse, say) into
+/// simulation precision.
+pub fn from_vec3(v: Vec3) -> RealVec3 {
+    #[cfg(not(feature = "f64"))]
+    return v;
+    #[cfg(feature = "f64")]
+    return v.as_dvec3();
+}
+
 macro_rules! impl_zero_for {
     ($Type:ty = $z:expr) => {
         impl $Type {
@@ -91,7 +146,10 @@ macro_rules! impl_binop_with {
 
 macro_rules! impl_scalar {
     ($Type:ty) => {
-        impl_from_for!(f32 => $Type);
+        impl_scalar!($Type: Real);
+    };
+    ($Type:ty : $Scalar:ty) => {
+        impl_from_for!($Scalar => $Type);
         impl_zero_for!($Type = 0.0);
         impl_add_sub_for!($Type);
         impl_binop!($Type {*} $Type = $Type);
@@ -101,12 +159,15 @@ macro_rules! impl_scalar {
 
 macro_rules! impl_vector {
     ($Type:ty) => {
-        impl_from_for!(Vec3 => $Type);
-        impl_zero_for!($Type = Vec3::ZERO);
+        impl_vector!($Type: RealVec3, Real);
+    };
+    ($Type:ty : $Vector:ty, $Scalar:ty) => {
+        impl_from_for!($Vector => $Type);
+        impl_zero_for!($Type = <$Vector>::ZERO);
         impl_add_sub_for!($Type);
-        impl_binop_with!(f32 {*} $Type = $Type { |a:f32, b: $Type| (a * b.0).into() });
-        impl_binop_with!($Type {*} f32 = $Type { |a: $Type, b:f32| (a.0 * b).into() });
-        impl_binop_with!($Type {/} f32 = $Type { |a: $Type, b:f32| (a.0 / b).into() });
+        impl_binop_with!($Scalar {*} $Type = $Type { |a: $Scalar, b: $Type| (a * b.0).into() });
+        impl_binop_with!($Type {*} $Scalar = $Type { |a: $Type, b: $Scalar| (a.0 * b).into() });
+        impl_binop_with!($Type {/} $Scalar = $Type { |a: $Type, b: $Scalar| (a.0 / b).into() });
     };
 }
 
@@ -123,41 +184,41 @@ macro_rules! impl_vector {
     PartialOrd,
 )]
 #[reflect(Resource, InspectorOptions)]
-pub struct Mass(#[inspector(min = 0.0)] pub f32);
+pub struct Mass(#[inspector(min = 0.0)] pub Real);
 
 impl_scalar!(Mass);
 
 #[derive(Component, Resource, Default, Reflect, InspectorOptions, Debug, Clone, Copy)]
 #[reflect(Resource, InspectorOptions)]
-pub struct Moment(pub Vec3);
+pub struct Moment(pub RealVec3);
 
 impl_vector!(Moment);
-impl_binop_with!(Mass {*} Vec3 = Moment { |a: Mass, b: Vec3| Moment(a.0 * b) });
-impl_binop_with!(Vec3 {*} Mass = Moment { |a: Vec3, b: Mass| Moment(a * b.0) });
-impl_binop_with!(Moment {/} Mass = Vec3 { |a: Moment, b: Mass| a.0 / b.0 });
+impl_binop_with!(Mass {*} RealVec3 = Moment { |a: Mass, b: RealVec3| Moment(a.0 * b) });
+impl_binop_with!(RealVec3 {*} Mass = Moment { |a: RealVec3, b: Mass| Moment(a * b.0) });
+impl_binop_with!(Moment {/} Mass = RealVec3 { |a: Moment, b: Mass| a.0 / b.0 });
 
-#[derive(Component, Resource, Default, Reflect, InspectorOptions, Clone, Copy)]
+#[derive(Component, Resource, Default, Reflect, InspectorOptions, Debug, Clone, Copy)]
 #[reflect(Resource, InspectorOptions)]
-pub struct Velocity(pub Vec3);
+pub struct Velocity(pub RealVec3);
 
 impl_vector!(Velocity);
 
-#[derive(Component, Resource, Default, Reflect, InspectorOptions, Clone, Copy)]
+#[derive(Component, Resource, Default, Reflect, InspectorOptions, Debug, Clone, Copy)]
 #[reflect(Resource, InspectorOptions)]
-pub struct Force(pub Vec3);
+pub struct Force(pub RealVec3);
 
 impl_vector!(Force);
 impl_binop!(Force {/} Mass = Acceleration);
 
 #[derive(Component, Resource, Default, Reflect, InspectorOptions, Debug, Clone, Copy)]
 #[reflect(Resource, InspectorOptions)]
-pub struct Radius(#[inspector(min = 0.0)] pub f32);
+pub struct Radius(#[inspector(min = 0.0)] pub Real);
 
 impl_scalar!(Radius);
 
 #[derive(Component, Resource, Default, Reflect, InspectorOptions, Debug, Clone, Copy)]
 #[reflect(Resource, InspectorOptions)]
-pub struct Momentum(pub Vec3);
+pub struct Momentum(pub RealVec3);
 
 impl_vector!(Momentum);
 impl_binop!(Momentum {/} Mass = Velocity);
@@ -166,18 +227,98 @@ impl_binop!(Velocity {*} Mass = Momentum);
 
 #[derive(Component, Resource, Default, Reflect, InspectorOptions, Debug, Clone, Copy)]
 #[reflect(Resource, InspectorOptions)]
-pub struct Acceleration(pub Vec3);
+pub struct Acceleration(pub RealVec3);
 
 impl_vector!(Acceleration);
 impl_binop!(Mass {*} Acceleration = Force);
 
 #[derive(Component, Resource, Default, Reflect, InspectorOptions, Debug, Clone, Copy)]
 #[reflect(Resource, InspectorOptions)]
-pub struct Time(pub f32);
+pub struct Time(pub Real);
 
 impl_scalar!(Time);
 impl_binop!(Acceleration {*} Time = Velocity);
 impl_binop!(Time {*} Acceleration = Velocity);
 impl_binop!(Force {*} Time = Momentum);
 impl_binop!(Time {*} Force = Momentum);
-impl_binop_with!(Velocity {*} Time = Vec3 { |a: Velocity, b: Time| a.0 * b.0 });
+impl_binop_with!(Velocity {*} Time = RealVec3 { |a: Velocity, b: Time| a.0 * b.0 });
+
+#[derive(Component, Resource, Default, Reflect, InspectorOptions, Debug, Clone, Copy)]
+#[reflect(Resource, InspectorOptions)]
+pub struct Position(pub RealVec3);
+
+impl_vector!(Position);
+
+/// The [`Position`] a body had at the start of the most recent fixed step. Used
+/// to interpolate the rendered `Transform` between physics states.
+#[derive(Component, Default, Reflect, Debug, Clone, Copy)]
+pub struct PreviousPosition(pub RealVec3);
+
+/// The [`Position`] a body had at the start of the current substep. Collision
+/// detection sweeps each body from here to its [`Position`].
+#[derive(Component, Default, Reflect, Debug, Clone, Copy)]
+pub struct SubstepStart(pub RealVec3);
+
+#[derive(Component, Resource, Default, Reflect, InspectorOptions, Debug, Clone, Copy)]
+#[reflect(Resource, InspectorOptions)]
+pub struct Energy(pub Real);
+
+impl_scalar!(Energy);
+
+#[derive(Component, Resource, Default, Reflect, InspectorOptions, Debug, Clone, Copy)]
+#[reflect(Resource, InspectorOptions)]
+pub struct AngularMomentum(pub RealVec3);
+
+impl_vector!(AngularMomentum);
+
+impl Mass {
+    pub fn kinetic_energy(self, vel: Velocity) -> Energy {
+        Energy(0.5 * self.0 * vel.0.length_squared())
+    }
+
+    /// Angular momentum about the origin of a point mass at `pos`.
+    pub fn angular_momentum(self, pos: Position, vel: Velocity) -> AngularMomentum {
+        AngularMomentum(self.0 * pos.0.cross(vel.0))
+    }
+}
+
+/// Rotation of a body about its own center, treated as a uniform sphere.
+#[derive(Component, Resource, Default, Reflect, InspectorOptions, Debug, Clone, Copy)]
+#[reflect(Resource, InspectorOptions)]
+pub struct Spin {
+    /// Axis of rotation scaled by the rate, in radians per unit time.
+    pub angular_velocity: RealVec3,
+    #[inspector(min = 0.0)]
+    pub moment_of_inertia: Real,
+}
+
+impl Spin {
+    /// A sphere of the given mass and radius which isn't rotating.
+    pub fn at_rest(mass: Mass, radius: Radius) -> Self {
+        Self {
+            angular_velocity: RealVec3::ZERO,
+            moment_of_inertia: 0.4 * mass.0 * radius.0 * radius.0,
+        }
+    }
+
+    /// A sphere of the given mass and radius carrying `angular_momentum`.
+    pub fn with_angular_momentum(
+        mass: Mass,
+        radius: Radius,
+        angular_momentum: AngularMomentum,
+    ) -> Self {
+        let at_rest = Self::at_rest(mass, radius);
+        Self {
+            angular_velocity: angular_momentum.0 / at_rest.moment_of_inertia,
+            ..at_rest
+        }
+    }
+
+    pub fn angular_momentum(self) -> AngularMomentum {
+        AngularMomentum(self.moment_of_inertia * self.angular_velocity)
+    }
+
+    pub fn kinetic_energy(self) -> Energy {
+        Energy(0.5 * self.moment_of_inertia * self.angular_velocity.length_squared())
+    }
+}
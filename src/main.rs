@@ -21,7 +21,7 @@ fn main() {
         .add_plugins((
             DefaultPlugins,
             PanOrbitCameraPlugin,
-            PlanetsPlugin,
+            PlanetsPlugin::default(),
             MyUiPlugin,
         ))
         .add_systems(Startup, (init_camera, spawn))
@@ -1,19 +1,108 @@
 use std::f32::consts::TAU;
 
-use bevy::prelude::*;
+use bevy::{ecs::system::EntityCommands, prelude::*};
 use rand::prelude::*;
 
-use crate::components::{self, Force, Mass, Radius, Velocity};
-
-use self::collisions::{CollisionGroup, CollisionGroups, CollisionResolutionPlugin};
-
+use crate::components::{
+    from_vec3, to_f32, to_vec3, AngularMomentum, Energy, Force, Mass, Position, PreviousPosition,
+    Radius, Real, RealVec3, Spin, SubstepStart, Velocity,
+};
+
+use self::{
+    adaptive::AdaptiveStep,
+    collisions::CollisionResolutionPlugin,
+    composition::{Composition, Densities},
+    diagnostics::{Conservation, DiagnosticsPlugin},
+    disk::{snow_line_overlay_system, SnowLineOverlay, Star},
+    envelope::EnvelopePlugin,
+    gas::{GasDragPlugin, Migration},
+    generator::{DiskGenerator, DiskGeneratorPlugin},
+    gravity::{GravitySolver, Softening},
+    integrator::{Body, IntegratorKind},
+    octree::{octree_overlay_system, OctreeOverlay},
+    orbit::{OrbitPlugin, OrbitalElements},
+    outcomes::CollisionModel,
+    particles::TestParticlePlugin,
+    pebbles::PebbleAccretionPlugin,
+    simulation::{PhysicsSet, SimClock, SimRng, SimulationPlugin, SimulationStep},
+    stars::{StarSystemPreset, StarsPlugin},
+};
+
+pub mod adaptive;
+mod broad_phase;
 mod collisions;
+pub mod composition;
+pub mod diagnostics;
+mod disjoint_set;
+pub mod disk;
+pub mod envelope;
+pub mod gas;
+pub mod generator;
+pub mod gravity;
+pub mod integrator;
+pub mod octree;
+pub mod orbit;
+pub mod outcomes;
+pub mod particles;
+pub mod pebbles;
+pub mod simulation;
+pub mod stars;
 
 #[derive(Resource)]
 pub struct Constants {
     pub mouse_spring_strength: f32,
-    pub grav_const: f32,
-    pub min_attraction_dist: f32,
+    pub grav_const: Real,
+    pub softening: Softening,
+    /// Separation below which [`Softening::None`] stops the force from growing.
+    pub min_attraction_dist: Real,
+    /// Softening length ε of [`Softening::Plummer`].
+    pub plummer_epsilon: Real,
+    /// Radius beyond which [`Softening::CubicSpline`] is exactly Newtonian.
+    pub spline_length: Real,
+    pub collision_model: CollisionModel,
+    /// Fraction of the approach speed kept by [`Outcome::Bounce`].
+    ///
+    /// [`Outcome::Bounce`]: outcomes::Outcome::Bounce
+    pub restitution: Real,
+    /// Catastrophic disruption threshold of an equal-mass impact, as a
+    /// multiple of the combined body's binding energy per unit mass (L&S's
+    /// c*).
+    pub disruption_strength: Real,
+    /// Most fragments a single disruptive impact can produce.
+    pub fragment_count: usize,
+    /// Debris lighter than this is folded back into the largest remnant.
+    pub min_fragment_mass: Real,
+    /// Whether test particles which hit a planet are removed.
+    pub accrete_test_particles: bool,
+    pub densities: Densities,
+    /// Whether planets grow by accreting pebbles drifting through the gas.
+    pub pebble_accretion: bool,
+    /// Mass of pebbles drifting in from the outer disk per unit time, before
+    /// the gas starts to dissipate.
+    pub pebble_flux: Real,
+    /// Stokes number of the pebbles: their stopping time in units of 1/Ω.
+    pub pebble_stokes_number: Real,
+    /// Mass at which planets stop accreting pebbles, where H/r is 0.05.
+    /// Lambrechts et al. (2014) find 20 Earth masses; this is scaled up to the
+    /// simulation's much heavier planets.
+    pub pebble_isolation_mass: Real,
+    /// Whether planets with massive enough cores take on gas envelopes.
+    pub gas_accretion: bool,
+    /// Core mass past which a planet's envelope can no longer hold itself up
+    /// and gas starts to run away onto it. About 10 Earth masses in a real
+    /// disk (Mizuno 1980), scaled up here like the isolation mass.
+    pub critical_core_mass: Real,
+    /// Kelvin–Helmholtz contraction time of the envelope at the critical core
+    /// mass. It shortens as M^-3 as the planet grows.
+    pub envelope_timescale: Real,
+    /// Mass at which a planet opens a gap in the gas and stops accreting it,
+    /// where H/r is 0.05.
+    pub gap_opening_mass: Real,
+    pub integrator: IntegratorKind,
+    pub gravity_solver: GravitySolver,
+    /// Barnes–Hut opening angle θ. Smaller is more accurate but slower; zero
+    /// degenerates to the exact pairwise sum.
+    pub opening_angle: Real,
 }
 
 impl Default for Constants {
@@ -21,12 +110,42 @@ impl Default for Constants {
         Self {
             mouse_spring_strength: 0.01,
             grav_const: 20.0,
+            softening: Softening::default(),
             min_attraction_dist: 0.001,
+            plummer_epsilon: 1.0,
+            spline_length: 2.8,
+            collision_model: CollisionModel::default(),
+            restitution: 0.5,
+            disruption_strength: 1.9,
+            fragment_count: 8,
+            min_fragment_mass: 0.5,
+            accrete_test_particles: true,
+            densities: Densities::default(),
+            pebble_accretion: false,
+            pebble_flux: 0.01,
+            pebble_stokes_number: 0.1,
+            pebble_isolation_mass: 20.0,
+            gas_accretion: false,
+            critical_core_mass: 30.0,
+            envelope_timescale: 500.0,
+            gap_opening_mass: 150.0,
+            integrator: IntegratorKind::default(),
+            gravity_solver: GravitySolver::default(),
+            opening_angle: 0.5,
         }
     }
 }
 
-pub struct PlanetsPlugin;
+#[derive(Default)]
+pub struct PlanetsPlugin {
+    /// The integrator the simulation starts with. Can be changed at runtime
+    /// through [`Constants::integrator`].
+    pub integrator: IntegratorKind,
+    pub gravity_solver: GravitySolver,
+    /// How the planets the simulation starts with are laid out. Can be changed
+    /// at runtime through the [`DiskGenerator`] resource.
+    pub disk: DiskGenerator,
+}
 
 impl Plugin for PlanetsPlugin {
     fn build(&self, app: &mut App) {
@@ -35,240 +154,363 @@ impl Plugin for PlanetsPlugin {
             .register_type::<Radius>()
             .register_type::<Velocity>()
             .register_type::<Force>()
+            .register_type::<Position>()
+            .register_type::<Energy>()
+            .register_type::<AngularMomentum>()
+            .register_type::<Spin>()
+            .register_type::<Composition>()
+            .register_type::<Star>()
             .add_event::<SpawnPlanetEvent>()
-            .init_resource::<Constants>()
-            .add_plugins(CollisionResolutionPlugin)
-            .add_systems(Startup, (spawn_planets, spawn_sun))
-            .add_systems(Update, (nbody_system,))
-            .add_systems(PostUpdate, (physics_system, spawn_planet_system));
+            .insert_resource(Constants {
+                integrator: self.integrator,
+                gravity_solver: self.gravity_solver,
+                ..default()
+            })
+            .init_resource::<OctreeOverlay>()
+            .init_resource::<SnowLineOverlay>()
+            .init_resource::<AdaptiveStep>()
+            .add_plugins((
+                SimulationPlugin,
+                CollisionResolutionPlugin,
+                DiagnosticsPlugin,
+                TestParticlePlugin,
+                GasDragPlugin,
+                PebbleAccretionPlugin,
+                EnvelopePlugin,
+                StarsPlugin,
+                OrbitPlugin,
+                DiskGeneratorPlugin {
+                    generator: self.disk,
+                },
+            ))
+            .add_systems(
+                SimulationStep,
+                (nbody_system, spin_system)
+                    .chain()
+                    .in_set(PhysicsSet::Integrate),
+            )
+            .add_systems(Update, (octree_overlay_system, snow_line_overlay_system))
+            .add_systems(PostUpdate, (spawn_planet_system, planet_appearance_system));
     }
 }
 
 #[derive(Component)]
 pub struct Planet;
 
-#[derive(Component)]
-pub struct Sun;
-
-const SUN_MASS: Mass = Mass(1000.0);
-
-fn spawn_sun(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-) {
-    let radius = radius_from_mass(SUN_MASS);
-
-    commands
-        .spawn((
-            PointLightBundle {
-                transform: Transform::from_translation(Vec3::ZERO),
-                point_light: PointLight {
-                    intensity: 50_000_000.0,
-                    range: 10_000.0,
-                    radius: 3.0,
-                    color: Color::ORANGE,
-                    shadows_enabled: true,
-                    ..default()
-                },
-                ..default()
-            },
-            Sun,
-            Planet,
-            Name::new("Sun"),
-            radius,
-            SUN_MASS,
-            Velocity::ZERO,
-            Force::ZERO,
-        ))
-        .with_children(|builder| {
-            builder.spawn(PbrBundle {
-                mesh: meshes.add(
-                    shape::UVSphere {
-                        radius: radius.0,
-                        ..default()
-                    }
-                    .try_into()
-                    .unwrap(),
-                ),
-                material: materials.add(StandardMaterial {
-                    base_color: Color::lch(1.0, 0.15, 50.0),
-                    emissive: Color::lch(1.5, 0.05, 74.0),
-                    ..default()
-                }),
-                transform: Transform::from_translation(Vec3::ZERO),
-                ..default()
-            });
-        });
-}
-
 #[derive(Event, Default, Clone, Copy)]
 pub struct SpawnPlanetEvent {
-    pub pos: Option<Vec3>,
+    pub pos: Option<RealVec3>,
     pub vel: Option<Velocity>,
     pub mass: Option<Mass>,
+    pub composition: Option<Composition>,
+    /// Places the planet on this orbit instead, overriding `pos` and `vel`.
+    pub orbit: Option<SpawnOrbit>,
 }
 
-pub fn radius_from_mass(mass: Mass) -> Radius {
-    Radius(3.0 * mass.0.cbrt())
+/// An orbit to spawn a planet on, about any other body.
+#[derive(Clone, Copy, Debug)]
+pub struct SpawnOrbit {
+    pub primary: Entity,
+    pub elements: OrbitalElements,
 }
 
-pub fn mass_from_radius(radius: Radius) -> Mass {
-    Mass((radius.0 / 3.0).powi(3))
+/// Density, in g/cm³, at which a body of mass `m` has radius `3∛m`. This ties
+/// the simulation's units of mass and length together.
+const REFERENCE_DENSITY: Real = 1.4;
+
+/// Radius of a body of the given mass and density (in g/cm³).
+pub fn radius_from_mass(mass: Mass, density: Real) -> Radius {
+    Radius(3.0 * (mass.0 * REFERENCE_DENSITY / density).cbrt())
+}
+
+/// Mass of a body of the given radius and density (in g/cm³).
+pub fn mass_from_radius(radius: Radius, density: Real) -> Mass {
+    Mass((radius.0 / 3.0).powi(3) * density / REFERENCE_DENSITY)
 }
 
+#[allow(clippy::too_many_arguments)]
 fn spawn_planet_system(
     mut ereader: EventReader<SpawnPlanetEvent>,
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     constants: Res<Constants>,
+    preset: Res<StarSystemPreset>,
+    stars: Query<(&Star, &Position, &Velocity, &Mass)>,
+    primaries: Query<(&Position, &Velocity, &Mass), With<Planet>>,
+    mut rng: ResMut<SimRng>,
 ) {
-    let mut rng = thread_rng();
+    let rng = &mut rng.0;
 
     for event in ereader.iter() {
         let pos = event.pos.unwrap_or_else(|| {
-            rng.gen_range(50.0..500.0)
-                * (Quat::from_axis_angle(Vec3::Y, rng.gen_range(0.0..TAU)).mul_vec3(Vec3::X)
-                    + rng.gen_range(-0.1..0.1) * Vec3::Y)
+            from_vec3(
+                to_f32(rng.gen_range(preset.planet_orbits()))
+                    * (Quat::from_axis_angle(Vec3::Y, rng.gen_range(0.0..TAU)).mul_vec3(Vec3::X)
+                        + rng.gen_range(-0.1..0.1) * Vec3::Y),
+            )
         });
 
         let mass = event
             .mass
             .unwrap_or_else(|| Mass(50.0 * rng.gen_range(0.0..1.0) + 2.0));
-        let radius = radius_from_mass(mass);
 
-        let vel = event.vel.unwrap_or_else(|| {
-            let orbit_speed = f32::sqrt(constants.grav_const * SUN_MASS.0 * pos.length_recip());
-            Velocity(-orbit_speed * pos.normalize().cross(Vec3::Y))
+        // An orbit about a primary that has since gone falls back on the rest.
+        let on_orbit = event.orbit.and_then(|orbit| {
+            let (primary_pos, &primary_vel, &primary_mass) = primaries.get(orbit.primary).ok()?;
+            let mu = constants.grav_const * (primary_mass + mass).0;
+            let (rel_pos, rel_vel) = orbit.elements.to_state(mu);
+            Some((primary_pos.0 + rel_pos, primary_vel + Velocity(rel_vel)))
         });
 
-        let material = StandardMaterial {
-            base_color: Color::Hsla {
-                hue: 360.0 * rng.gen_range(0.0..1.0),
-                saturation: 0.5,
-                lightness: 0.5,
-                alpha: 1.0,
-            },
-            perceptual_roughness: 0.9,
-            metallic: 0.5,
-            reflectance: 0.1,
-            fog_enabled: true,
-            ..default()
-        };
+        let (pos, vel) = on_orbit.unwrap_or_else(|| {
+            let vel = event.vel.unwrap_or_else(|| {
+                let stars = stars.iter().map(|(_, pos, vel, mass)| (pos, vel, mass));
+                stars::circular_velocity(&constants, stars, pos)
+            });
+            (pos, vel)
+        });
 
-        commands.spawn((
-            Planet,
-            Name::new(format!("Planet (m={:.1})", mass.0)),
-            radius,
-            mass,
+        let composition = event.composition.unwrap_or_else(|| {
+            let stars = stars.iter().map(|(star, pos, ..)| (star, pos));
+            Composition::condensed_at(disk::temperature(stars, pos), rng)
+        });
+
+        spawn_planet(
+            &mut commands,
+            &mut meshes,
+            &mut materials,
+            &constants.densities,
+            pos,
             vel,
-            Force::ZERO,
-            PbrBundle {
-                mesh: meshes.add(
-                    shape::UVSphere {
-                        radius: radius.0,
-                        ..default()
-                    }
-                    .try_into()
-                    .unwrap(),
-                ),
-                material: materials.add(material),
-                transform: Transform::from_translation(pos),
-                ..default()
-            },
-        ));
+            mass,
+            composition,
+        );
     }
 }
 
-fn spawn_planets(mut ewriter: EventWriter<SpawnPlanetEvent>) {
-    const N: usize = 25;
-    ewriter.send_batch(std::iter::repeat(SpawnPlanetEvent::default()).take(N));
+/// Sphere mesh for a planet of the given radius.
+fn planet_mesh(radius: Radius) -> Mesh {
+    shape::UVSphere {
+        radius: to_f32(radius.0),
+        ..default()
+    }
+    .into()
+}
+
+/// Material a planet of the given composition is drawn with.
+fn planet_material(composition: &Composition) -> StandardMaterial {
+    // Gas giants show smooth, glossy cloud tops rather than bare rock.
+    let gas = to_f32(composition.gas);
+    StandardMaterial {
+        base_color: composition.color(),
+        perceptual_roughness: 0.9 - 0.5 * gas,
+        metallic: to_f32(composition.metal) * (1.0 - gas),
+        reflectance: 0.1 + 0.3 * gas,
+        fog_enabled: true,
+        ..default()
+    }
 }
 
-fn physics_system(
-    mut query: Query<(&mut Transform, &mut Velocity, &Mass, &mut Force)>,
-    time: Res<Time>,
+/// Spawns a planet of the given mass and composition, with a sphere mesh and
+/// material to match.
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_planet<'w, 's, 'a>(
+    commands: &'a mut Commands<'w, 's>,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    densities: &Densities,
+    pos: RealVec3,
+    vel: Velocity,
+    mass: Mass,
+    composition: Composition,
+) -> EntityCommands<'w, 's, 'a> {
+    let radius = radius_from_mass(mass, composition.density(densities));
+
+    commands.spawn((
+        Planet,
+        Name::new(format!("Planet (m={:.1})", mass.0)),
+        radius,
+        mass,
+        composition,
+        Position(pos),
+        PreviousPosition(pos),
+        SubstepStart(pos),
+        vel,
+        Force::ZERO,
+        Migration::default(),
+        OrbitalElements::default(),
+        Spin::at_rest(mass, radius),
+        PbrBundle {
+            mesh: meshes.add(planet_mesh(radius)),
+            material: materials.add(planet_material(&composition)),
+            transform: Transform::from_translation(to_vec3(pos)),
+            ..default()
+        },
+    ))
+}
+
+pub type AccretingPlanetsData<'a, 'b, 'c, 'd, 'e, 'f> = (
+    Entity,
+    &'a Position,
+    &'b Velocity,
+    &'c mut Mass,
+    &'d mut Radius,
+    &'e mut Composition,
+    &'f mut Spin,
+);
+
+/// Adds `accreted` mass of material of the given composition to a planet,
+/// resizing it to match. The material is taken to bring no angular momentum of
+/// its own, so the planet's spin slows as it grows. What it adds to the totals,
+/// counting its potential energy against the other `bodies`, is recorded in
+/// [`Conservation`].
+pub fn accrete(
+    constants: &Constants,
+    conservation: &mut Conservation,
+    bodies: &[(Entity, RealVec3, Mass)],
+    (entity, pos, vel, mass, radius, composition, spin): (
+        Entity,
+        &Position,
+        &Velocity,
+        &mut Mass,
+        &mut Radius,
+        &mut Composition,
+        &mut Spin,
+    ),
+    accreted: Mass,
+    accreted_composition: Composition,
+) {
+    let before = (pos.0, *vel, *mass, *spin);
+    let angular_momentum = spin.angular_momentum();
+
+    *composition = Composition::mix([(*mass, *composition), (accreted, accreted_composition)]);
+    *mass += accreted;
+    *radius = radius_from_mass(*mass, composition.density(&constants.densities));
+    *spin = Spin::with_angular_momentum(*mass, *radius, angular_momentum);
+
+    let others = bodies
+        .iter()
+        .filter(|&&(other, ..)| other != entity)
+        .map(|&(_, pos, mass)| (pos, mass));
+    let (energy, momentum, angular_momentum) =
+        diagnostics::accretion_change(constants, others, before, (pos.0, *vel, *mass, *spin));
+    conservation.disk_energy += energy;
+    conservation.disk_momentum += momentum;
+    conservation.disk_angular_momentum += angular_momentum;
+}
+
+type PlanetAppearanceData<'a, 'b, 'c, 'd> = (
+    Ref<'a, Radius>,
+    Ref<'b, Composition>,
+    &'c Handle<Mesh>,
+    &'d Handle<StandardMaterial>,
+);
+
+/// Keeps each planet's mesh and material in step with its size and
+/// composition as collisions and accretion change them.
+fn planet_appearance_system(
+    planets: Query<PlanetAppearanceData, With<Planet>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
 ) {
-    let dt = components::Time(time.delta_seconds());
-    for (mut pos, mut vel, mass, mut net_force) in &mut query {
-        let acc = *net_force / *mass;
-        *vel += acc * dt;
-        pos.translation += *vel * dt;
-        *net_force = Force::ZERO;
+    for (radius, composition, mesh, material) in &planets {
+        // Freshly spawned planets were given a matching mesh and material.
+        if radius.is_changed() && !radius.is_added() {
+            if let Some(mesh) = meshes.get_mut(mesh) {
+                *mesh = planet_mesh(*radius);
+            }
+        }
+
+        if composition.is_changed() && !composition.is_added() {
+            if let Some(material) = materials.get_mut(material) {
+                *material = planet_material(&composition);
+            }
+        }
     }
 }
 
 type NBodyPlanetsData<'a, 'b, 'c, 'd, 'e> = (
-    Entity,
-    &'a Transform,
-    &'b Mass,
-    &'c Radius,
-    &'d Velocity,
+    &'a mut Position,
+    &'b mut Velocity,
+    &'c Mass,
+    &'d Radius,
     &'e mut Force,
 );
 
+/// Advances every planet by one substep using the active integrator. Gravity is
+/// recomputed whenever the integrator asks for forces; whatever other systems
+/// accumulated into `Force` is added on top and held constant over the step.
+/// What those forces add to the bodies is recorded in [`Conservation`].
 fn nbody_system(
-    mut planets_mut: Query<NBodyPlanetsData, With<Planet>>,
+    mut planets: Query<NBodyPlanetsData, With<Planet>>,
     constants: Res<Constants>,
-    mut collision_groups: ResMut<CollisionGroups>,
+    clock: Res<SimClock>,
+    mut octree_overlay: ResMut<OctreeOverlay>,
+    mut adaptive: ResMut<AdaptiveStep>,
+    mut conservation: ResMut<Conservation>,
 ) {
-    let mut it = planets_mut.iter_combinations_mut();
-    while let Some([(e1, tsf1, &m1, &r1, &v1, mut f_net1), (e2, tsf2, &m2, &r2, &v2, mut f_net2)]) =
-        it.fetch_next()
-    {
-        let (tsl1, tsl2) = (tsf1.translation, tsf2.translation);
-
-        let sat_to_parent = tsl2 - tsl1;
-        let radii_sum = r1 + r2;
-
-        // Collision detection:
-        if sat_to_parent.length_squared() < radii_sum.0 * radii_sum.0 {
-            use collisions::PlanetInfo;
-
-            let p1 = PlanetInfo {
-                entity: e1,
-                mass: m1,
-                vel: v1,
-                pos: tsl1,
-            };
-
-            let p2 = PlanetInfo {
-                entity: e2,
-                mass: m2,
-                vel: v2,
-                pos: tsl2,
+    let (mut bodies, external): (Vec<Body>, Vec<Force>) = planets
+        .iter()
+        .map(|(&pos, &vel, &mass, &radius, &force)| {
+            let body = Body {
+                pos,
+                vel,
+                mass,
+                radius,
             };
-
-            let (larger, smaller) = if m1 > m2 { (p1, p2) } else { (p2, p1) };
-
-            collision_groups
-                .map
-                .entry(larger.entity)
-                .or_insert(CollisionGroup {
-                    largest: larger,
-                    members: vec![],
-                })
-                .members
-                .push(smaller);
-
-            // Skip rest of force computation.
-            continue;
+            (body, force)
+        })
+        .unzip();
+
+    let mut last_tree = None;
+    let mut force_fn = |bodies: &[Body], forces: &mut [Force]| {
+        last_tree = gravity::forces(bodies, &constants, forces);
+        for (f, &ext) in forces.iter_mut().zip(&external) {
+            *f += ext;
         }
+    };
+
+    let integrator = constants.integrator.integrator();
+    if adaptive.enabled {
+        adaptive.advance(
+            integrator,
+            &mut bodies,
+            clock.dt,
+            constants.grav_const,
+            &mut force_fn,
+        );
+    } else {
+        integrator.step(&mut bodies, clock.dt, &mut force_fn);
+    }
 
-        let force = {
-            let sat_mass = m1.0;
-            let parent_mass = m2.0;
-            let grav_const = constants.grav_const;
-            let min_dist = constants.min_attraction_dist;
-            let min_dist_sq = min_dist * min_dist;
-            let toward_parent = sat_to_parent.normalize_or_zero();
-            let r_sq = sat_to_parent.length_squared();
+    if octree_overlay.enabled {
+        octree_overlay.cells = last_tree
+            .iter()
+            .flat_map(|tree| tree.cells())
+            .map(|(center, half_size)| (to_vec3(center), to_f32(half_size)))
+            .collect();
+    }
 
-            grav_const * sat_mass * parent_mass * toward_parent / r_sq.max(min_dist_sq)
-        };
+    for ((mut pos, mut vel, _, _, mut force), body) in planets.iter_mut().zip(&bodies) {
+        // The force is constant over the step, so its work is exact. The
+        // torque is taken about the midpoint of the path.
+        let path = body.pos.0 - pos.0;
+        let midpoint = pos.0 + 0.5 * path;
+        conservation.disk_energy += Energy(force.0.dot(path));
+        conservation.disk_momentum += *force * clock.dt;
+        conservation.disk_angular_momentum += AngularMomentum(midpoint.cross(force.0) * clock.dt.0);
+
+        *pos = body.pos;
+        *vel = body.vel;
+        *force = Force::ZERO;
+    }
+}
 
-        *f_net1 += Force(force);
-        *f_net2 -= Force(force);
+/// Turns each planet's mesh by its [`Spin`] over the substep.
+fn spin_system(mut planets: Query<(&mut Transform, &Spin), With<Planet>>, clock: Res<SimClock>) {
+    for (mut tsf, spin) in &mut planets {
+        let turn = to_vec3(spin.angular_velocity * clock.dt.0);
+        tsf.rotation = (Quat::from_scaled_axis(turn) * tsf.rotation).normalize();
     }
 }
@@ -0,0 +1,137 @@
+use bevy::prelude::*;
+
+use crate::components::{Real, Time};
+
+use super::integrator::{Body, ForceFn, Integrator};
+
+/// Step size controller for close encounters.
+///
+/// Each substep is covered by as many integrator steps as it takes to keep the
+/// local position error below `tolerance`. Steps are proposed from the shortest
+/// encounter timescale between any two bodies, then checked by step doubling:
+/// one step of `h` is compared against two of `h / 2`, and the step is rejected
+/// and retried with a smaller `h` if they disagree by too much.
+#[derive(Resource)]
+pub struct AdaptiveStep {
+    pub enabled: bool,
+    /// Largest allowed position error per step, in world units.
+    pub tolerance: Real,
+    /// Fraction of the shortest encounter timescale a single step may span.
+    pub eta: Real,
+    /// Steps are never shrunk below this, even if the error is still too large.
+    pub min_dt: Real,
+    /// The step size most recently accepted.
+    pub dt: Time,
+    /// Total number of steps thrown away because their error was too large.
+    pub rejected_steps: u64,
+    /// The step size the controller will try next.
+    next_dt: Real,
+}
+
+impl Default for AdaptiveStep {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            tolerance: 1e-3,
+            eta: 0.05,
+            min_dt: 1e-4,
+            dt: Time(0.0),
+            rejected_steps: 0,
+            next_dt: Real::INFINITY,
+        }
+    }
+}
+
+/// Never grow or shrink the step by more than this factor at once.
+const MAX_FACTOR: Real = 2.0;
+const MIN_FACTOR: Real = 0.1;
+/// Aim a little below the tolerance so the next step is less likely to fail.
+const SAFETY: Real = 0.9;
+
+impl AdaptiveStep {
+    /// Advances `bodies` by exactly `span`.
+    pub fn advance(
+        &mut self,
+        integrator: &dyn Integrator,
+        bodies: &mut Vec<Body>,
+        span: Time,
+        grav_const: Real,
+        forces: &mut ForceFn,
+    ) {
+        let exponent = 1.0 / (integrator.order() + 1) as Real;
+        let mut elapsed = 0.0;
+
+        while elapsed < span.0 {
+            let encounter = self.eta * encounter_time(bodies, grav_const);
+            let proposed = self.next_dt.min(encounter).max(self.min_dt);
+            let remaining = span.0 - elapsed;
+            let h = proposed.min(remaining);
+
+            let mut full = bodies.clone();
+            integrator.step(&mut full, Time(h), forces);
+
+            let mut halves = bodies.clone();
+            integrator.step(&mut halves, Time(0.5 * h), forces);
+            integrator.step(&mut halves, Time(0.5 * h), forces);
+
+            let error = full
+                .iter()
+                .zip(&halves)
+                .map(|(a, b)| a.pos.0.distance(b.pos.0))
+                .fold(0.0, Real::max);
+
+            let factor = if error > 0.0 {
+                SAFETY * (self.tolerance / error).powf(exponent)
+            } else {
+                MAX_FACTOR
+            };
+
+            if error > self.tolerance && h > self.min_dt {
+                self.rejected_steps += 1;
+                self.next_dt = h * factor.max(MIN_FACTOR);
+                continue;
+            }
+
+            *bodies = halves;
+            // Snap to the end exactly so rounding can't leave a sliver behind.
+            elapsed = if h >= remaining { span.0 } else { elapsed + h };
+            self.dt = Time(h);
+
+            // A step cut short to land on the end of the span says nothing about
+            // whether a longer one would have worked.
+            self.next_dt = if h < proposed {
+                proposed
+            } else {
+                h * factor.clamp(MIN_FACTOR, MAX_FACTOR)
+            };
+        }
+    }
+}
+
+/// The shortest timescale on which any pair of bodies can change much: the
+/// smaller of their mutual free-fall time and the time to cross their current
+/// separation. Overlapping pairs are skipped since they're about to merge.
+pub fn encounter_time(bodies: &[Body], grav_const: Real) -> Real {
+    let mut shortest = Real::INFINITY;
+
+    for (i, b1) in bodies.iter().enumerate() {
+        for b2 in &bodies[i + 1..] {
+            let r = b1.pos.0.distance(b2.pos.0);
+            if r < (b1.radius + b2.radius).0 {
+                continue;
+            }
+
+            let total_mass = (b1.mass + b2.mass).0;
+            if total_mass > 0.0 && grav_const > 0.0 {
+                shortest = shortest.min((r * r * r / (grav_const * total_mass)).sqrt());
+            }
+
+            let rel_speed = (b1.vel - b2.vel).0.length();
+            if rel_speed > 0.0 {
+                shortest = shortest.min(r / rel_speed);
+            }
+        }
+    }
+
+    shortest
+}
@@ -0,0 +1,88 @@
+use crate::components::RealVec3;
+
+/// An axis-aligned bounding box.
+#[derive(Debug, Clone, Copy)]
+pub struct Aabb {
+    pub min: RealVec3,
+    pub max: RealVec3,
+}
+
+impl Aabb {
+    fn overlaps(&self, other: &Aabb) -> bool {
+        self.min.cmple(other.max).all() && other.min.cmple(self.max).all()
+    }
+}
+
+/// Sweep-and-prune: every pair of indices `(i, j)` with `i < j` whose boxes
+/// overlap.
+///
+/// The boxes are sorted along x, and each is only tested against the boxes
+/// whose x extent it overlaps. For bodies scattered through a disk this is
+/// close to O(n log n), and it makes no assumptions about how big the boxes
+/// are relative to each other.
+pub fn overlapping_pairs(boxes: &[Aabb]) -> Vec<(usize, usize)> {
+    let mut order = (0..boxes.len()).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| boxes[a].min.x.total_cmp(&boxes[b].min.x));
+
+    let mut pairs = Vec::new();
+    let mut active: Vec<usize> = Vec::new();
+
+    for &i in &order {
+        let min_x = boxes[i].min.x;
+        active.retain(|&j| boxes[j].max.x >= min_x);
+
+        for &j in &active {
+            if boxes[i].overlaps(&boxes[j]) {
+                pairs.push((i.min(j), i.max(j)));
+            }
+        }
+
+        active.push(i);
+    }
+
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::components::from_f32;
+
+    use super::*;
+
+    fn cube(x: f32, y: f32, half_size: f32) -> Aabb {
+        let center = RealVec3::new(from_f32(x), from_f32(y), 0.0);
+        let half = RealVec3::splat(from_f32(half_size));
+        Aabb {
+            min: center - half,
+            max: center + half,
+        }
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let boxes = [
+            cube(0.0, 0.0, 1.0),
+            cube(1.5, 0.0, 1.0),
+            cube(1.5, 5.0, 1.0),
+            cube(-20.0, 0.0, 30.0),
+            cube(8.0, 8.0, 0.5),
+            cube(3.0, 0.5, 0.6),
+        ];
+
+        let mut expected = vec![];
+        for i in 0..boxes.len() {
+            for j in (i + 1)..boxes.len() {
+                if boxes[i].overlaps(&boxes[j]) {
+                    expected.push((i, j));
+                }
+            }
+        }
+
+        let mut pairs = overlapping_pairs(&boxes);
+        pairs.sort();
+
+        assert_eq!(pairs, expected);
+        assert!(pairs.contains(&(0, 1)));
+        assert!(!pairs.contains(&(1, 2)));
+    }
+}
@@ -1,14 +1,25 @@
-use bevy::{
-    prelude::{shape::UVSphere, *},
-    utils::HashMap,
-};
+use bevy::{prelude::*, utils::HashMap};
+
+use rand::Rng;
 
-use crate::{
-    components::{Mass, Moment, Momentum, Radius, Velocity},
-    planet::radius_from_mass,
+use crate::components::{
+    consts, AngularMomentum, Mass, Moment, Momentum, Position, Radius, Real, RealVec3, Spin,
+    SubstepStart, Time, Velocity,
 };
 
-use super::Planet;
+use super::{
+    broad_phase::{self, Aabb},
+    composition::{Composition, Densities},
+    diagnostics::{self, Conservation},
+    disjoint_set::DisjointSet,
+    disk::Star,
+    outcomes::{self, CollisionModel, Impact, Outcome},
+    radius_from_mass,
+    simulation::{PhysicsSet, SimClock, SimRng, SimulationStep},
+    spawn_planet,
+    stars::{accrete_onto_star, StellarAccretion},
+    Constants, Planet,
+};
 
 pub struct CollisionResolutionPlugin;
 
@@ -16,7 +27,12 @@ impl Plugin for CollisionResolutionPlugin {
     fn build(&self, app: &mut App) {
         app // <autoformat ignore>
             .init_resource::<CollisionGroups>()
-            .add_systems(PostUpdate, collision_resolution_system);
+            .add_systems(
+                SimulationStep,
+                (collision_detection_system, collision_resolution_system)
+                    .chain()
+                    .in_set(PhysicsSet::Collisions),
+            );
     }
 }
 
@@ -25,82 +41,845 @@ pub struct CollisionGroups {
     pub map: HashMap<Entity, CollisionGroup>,
 }
 
+/// A connected cluster of overlapping planets, all of which merge into
+/// `largest` this step.
 pub struct CollisionGroup {
     pub largest: PlanetInfo,
     pub members: Vec<PlanetInfo>,
+    /// Simulated time left in the substep after the first contact in the
+    /// group. Every planet's `pos` is where it was at that contact.
+    pub remaining: Time,
 }
 
 impl CollisionGroup {
     fn iter_all_planets(&self) -> impl Iterator<Item = &PlanetInfo> {
         std::iter::once(&self.largest).chain(self.members.iter())
     }
+
+    /// Mass, velocity, center of mass, spin and composition of the single body
+    /// the group merges into. Mass, linear momentum, angular momentum and the
+    /// mass of each material are conserved.
+    pub fn merged(&self, densities: &Densities) -> (Mass, Velocity, RealVec3, Spin, Composition) {
+        let total_mass = self.iter_all_planets().map(|p| p.mass).sum::<Mass>();
+
+        let total_momentum = self
+            .iter_all_planets()
+            .map(|p| p.mass * p.vel)
+            .sum::<Momentum>();
+
+        let center_of_mass = self
+            .iter_all_planets()
+            .map(|g| g.pos * g.mass)
+            .sum::<Moment>()
+            / total_mass;
+
+        let composition =
+            Composition::mix(self.iter_all_planets().map(|p| (p.mass, p.composition)));
+
+        let new_v = total_momentum / total_mass;
+        let spin = Spin::with_angular_momentum(
+            total_mass,
+            radius_from_mass(total_mass, composition.density(densities)),
+            self.angular_momentum_about(center_of_mass, new_v),
+        );
+
+        (total_mass, new_v, center_of_mass, spin, composition)
+    }
+
+    /// Total angular momentum of the group, spins included, about `center`
+    /// moving at `vel`.
+    fn angular_momentum_about(&self, center: RealVec3, vel: Velocity) -> AngularMomentum {
+        self.iter_all_planets()
+            .map(|p| {
+                p.mass
+                    .angular_momentum(Position(p.pos - center), p.vel - vel)
+                    + p.spin.angular_momentum()
+            })
+            .sum()
+    }
 }
 
 pub struct PlanetInfo {
     pub entity: Entity,
     pub mass: Mass,
     pub vel: Velocity,
-    pub pos: Vec3,
+    pub pos: RealVec3,
+    /// Where the planet was at the start of the substep.
+    pub start: RealVec3,
+    pub radius: Radius,
+    pub spin: Spin,
+    pub composition: Composition,
+}
+
+impl PlanetInfo {
+    /// Box around everywhere the planet touched during the substep.
+    fn swept_bounds(&self) -> Aabb {
+        let r = RealVec3::splat(self.radius.0);
+        Aabb {
+            min: self.start.min(self.pos) - r,
+            max: self.start.max(self.pos) + r,
+        }
+    }
+
+    /// The fraction of the substep after which `self` and `other` first touch,
+    /// treating both as spheres moving in straight lines from `start` to
+    /// `pos`. Unlike an overlap test at the end of the step, this catches
+    /// bodies which pass right through each other.
+    fn contact_time(&self, other: &PlanetInfo) -> Option<Real> {
+        let radii_sum = (self.radius + other.radius).0;
+        let gap = other.start - self.start;
+        let closing = (other.pos - other.start) - (self.pos - self.start);
+
+        // Solve |gap + closing t|² = radii_sum² for the earliest t.
+        let c = gap.length_squared() - radii_sum * radii_sum;
+        if c < 0.0 {
+            return Some(0.0);
+        }
+
+        let a = closing.length_squared();
+        let half_b = gap.dot(closing);
+        if half_b >= 0.0 || a == 0.0 {
+            return None;
+        }
+
+        let discriminant = half_b * half_b - a * c;
+        if discriminant < 0.0 {
+            return None;
+        }
+
+        let t = (-half_b - discriminant.sqrt()) / a;
+        (t <= 1.0).then_some(t)
+    }
+}
+
+type CollisionDetectionPlanetsData<'a, 'b, 'c, 'd, 'e, 'f, 'g> = (
+    Entity,
+    &'a Position,
+    &'b SubstepStart,
+    &'c Mass,
+    &'d Radius,
+    &'e Velocity,
+    &'f Spin,
+    &'g Composition,
+);
+
+fn collision_detection_system(
+    planets: Query<CollisionDetectionPlanetsData, With<Planet>>,
+    mut collision_groups: ResMut<CollisionGroups>,
+    clock: Res<SimClock>,
+) {
+    let planets = planets
+        .iter()
+        .map(
+            |(entity, pos, start, &mass, &radius, &vel, &spin, &composition)| PlanetInfo {
+                entity,
+                mass,
+                vel,
+                pos: pos.0,
+                start: start.0,
+                radius,
+                spin,
+                composition,
+            },
+        )
+        .collect();
+
+    for group in find_collision_groups(planets, clock.dt) {
+        collision_groups.map.insert(group.largest.entity, group);
+    }
+}
+
+/// Splits `planets` into clusters connected by chains of contacts during the
+/// substep of length `dt`, so that every planet ends up in at most one group.
+/// Each group is wound back to its first contact. Planets which touch nothing
+/// are dropped.
+pub fn find_collision_groups(planets: Vec<PlanetInfo>, dt: Time) -> Vec<CollisionGroup> {
+    let mut clusters = DisjointSet::new(planets.len());
+    let mut first_contact = vec![Real::INFINITY; planets.len()];
+
+    let bounds = planets
+        .iter()
+        .map(PlanetInfo::swept_bounds)
+        .collect::<Vec<_>>();
+
+    for (i, j) in broad_phase::overlapping_pairs(&bounds) {
+        if let Some(t) = planets[i].contact_time(&planets[j]) {
+            clusters.union(i, j);
+            first_contact[i] = first_contact[i].min(t);
+            first_contact[j] = first_contact[j].min(t);
+        }
+    }
+
+    let mut planets = planets.into_iter().map(Some).collect::<Vec<_>>();
+
+    clusters
+        .groups()
+        .into_iter()
+        .map(|indices| {
+            let t = indices
+                .iter()
+                .map(|&i| first_contact[i])
+                .fold(Real::INFINITY, Real::min);
+
+            let mut members = indices
+                .into_iter()
+                .filter_map(|i| planets[i].take())
+                .collect::<Vec<_>>();
+
+            for planet in &mut members {
+                planet.pos = planet.start.lerp(planet.pos, t);
+            }
+
+            let largest_idx = (1..members.len()).fold(0, |best, k| {
+                if members[k].mass > members[best].mass {
+                    k
+                } else {
+                    best
+                }
+            });
+
+            CollisionGroup {
+                largest: members.remove(largest_idx),
+                members,
+                remaining: Time((1.0 - t) * dt.0),
+            }
+        })
+        .collect()
 }
 
-type CollisionResolutionPlanetsData<'a, 'b, 'c, 'd, 'e> = (
+type CollisionResolutionPlanetsData<'a, 'b, 'c, 'd, 'e, 'f> = (
     Entity,
-    &'a mut Handle<Mesh>,
-    &'b mut Radius,
-    &'c mut Velocity,
-    &'d mut Mass,
-    &'e mut Transform,
+    &'a mut Radius,
+    &'b mut Velocity,
+    &'c mut Mass,
+    &'d mut Position,
+    &'e mut Spin,
+    &'f mut Composition,
 );
 
+/// Marks a planet which was thrown off by a collision rather than added from
+/// outside the simulation.
+#[derive(Component)]
+pub struct Fragment;
+
+/// What a collision group turns into.
+struct Resolution {
+    /// The new state of every member of the group which survives. The rest are
+    /// despawned.
+    survivors: Vec<(Entity, RealVec3, Velocity, Mass, Spin, Composition)>,
+    /// New bodies thrown off by the impact. They start out not spinning.
+    fragments: Vec<(RealVec3, Velocity, Mass, Composition)>,
+}
+
+impl Resolution {
+    fn merge(densities: &Densities, group: &CollisionGroup) -> Self {
+        let (total_mass, new_v, center_of_mass, spin, composition) = group.merged(densities);
+        Self {
+            survivors: vec![(
+                group.largest.entity,
+                center_of_mass,
+                new_v,
+                total_mass,
+                spin,
+                composition,
+            )],
+            fragments: vec![],
+        }
+    }
+
+    /// The projectile flies on past the target, shedding what it loses to
+    /// [`outcomes::hit_and_run_remnant`] as fragments. The two are carried on
+    /// along the projectile's path, about their center of mass, until nothing
+    /// touches the target any more, so the same impact isn't picked up again.
+    fn hit_and_run(
+        constants: &Constants,
+        target: &PlanetInfo,
+        projectile: &PlanetInfo,
+        impact: &Impact,
+        twist: Real,
+    ) -> Option<Self> {
+        let remnant = outcomes::hit_and_run_remnant(constants, target, projectile, impact);
+        let fragments = outcomes::fragment_masses(constants, projectile.mass - remnant);
+        let remnant = projectile.mass - fragments.iter().copied().sum::<Mass>();
+        let composition = projectile.composition;
+        let density = composition.density(&constants.densities);
+
+        // Break the projectile up about its own center, as if it were alone.
+        let own_escape_speed =
+            (2.0 * constants.grav_const * projectile.mass.0 / projectile.radius.0).sqrt();
+        let bodies = outcomes::scatter_fragments(
+            RealVec3::ZERO,
+            projectile.vel,
+            impact.normal,
+            own_escape_speed,
+            remnant,
+            &fragments,
+            density,
+            twist,
+        );
+
+        let clearance = 1.05
+            * (target.radius.0
+                + bodies
+                    .iter()
+                    .map(|&(offset, _, mass)| offset.length() + radius_from_mass(mass, density).0)
+                    .fold(0.0, Real::max));
+
+        // How far along the relative velocity the projectile has to go before
+        // it is `clearance` from the target's center.
+        let rel_pos = projectile.pos - target.pos;
+        let dir = (projectile.vel - target.vel).0.normalize_or_zero();
+        let along = rel_pos.dot(dir);
+        let travel =
+            -along + (along * along - rel_pos.length_squared() + clearance * clearance).sqrt();
+
+        let total_mass = target.mass + projectile.mass;
+        let target_pos = target.pos - travel * projectile.mass.0 / total_mass.0 * dir;
+        let projectile_pos = projectile.pos + travel * target.mass.0 / total_mass.0 * dir;
+
+        // The remnant's spin takes up whatever angular momentum the break-up
+        // gives the pieces about the projectile's center.
+        let carried_away = bodies
+            .iter()
+            .map(|&(offset, vel, mass)| {
+                mass.angular_momentum(Position(offset), vel - projectile.vel)
+            })
+            .sum::<AngularMomentum>();
+
+        let mut bodies = bodies
+            .into_iter()
+            .map(|(offset, vel, mass)| (projectile_pos + offset, vel, mass, composition));
+
+        let (pos, vel, mass, _) = bodies.next()?;
+        let spin = Spin::with_angular_momentum(
+            mass,
+            radius_from_mass(mass, density),
+            projectile.spin.angular_momentum() - carried_away,
+        );
+
+        Some(Self {
+            survivors: vec![
+                (
+                    target.entity,
+                    target_pos,
+                    target.vel,
+                    target.mass,
+                    target.spin,
+                    target.composition,
+                ),
+                (projectile.entity, pos, vel, mass, spin, composition),
+            ],
+            fragments: bodies.collect(),
+        })
+    }
+
+    /// Merges the whole group into `star`, which needn't be the largest. The
+    /// star stays all gas, so it grows with whatever it swallows rather than
+    /// taking on the rock and metal and shrinking.
+    fn swallow(densities: &Densities, group: &CollisionGroup, star: Entity) -> Self {
+        let (total_mass, new_v, center_of_mass, spin, _) = group.merged(densities);
+        let composition = Composition::GAS;
+        let spin = Spin::with_angular_momentum(
+            total_mass,
+            radius_from_mass(total_mass, composition.density(densities)),
+            spin.angular_momentum(),
+        );
+        Self {
+            survivors: vec![(star, center_of_mass, new_v, total_mass, spin, composition)],
+            fragments: vec![],
+        }
+    }
+}
+
+/// Decides what happens to `group`, or returns `None` if nothing does. Only
+/// two-body collisions are run through [`CollisionModel::LeinhardtStewart`];
+/// pileups of three or more always merge.
+fn resolve(constants: &Constants, group: &CollisionGroup, twist: Real) -> Option<Resolution> {
+    let (target, projectile) = match group.members.as_slice() {
+        [projectile] if constants.collision_model == CollisionModel::LeinhardtStewart => {
+            (&group.largest, projectile)
+        }
+        // L&S only describe two-body impacts, so anything bigger merges
+        // whatever its impact speeds and angles.
+        _ => return Some(Resolution::merge(&constants.densities, group)),
+    };
+
+    let impact = Impact::new(constants, target, projectile);
+
+    let largest_remnant = match outcomes::classify(constants, target, projectile)? {
+        Outcome::Merge => return Some(Resolution::merge(&constants.densities, group)),
+        Outcome::HitAndRun => {
+            return Resolution::hit_and_run(constants, target, projectile, &impact, twist)
+        }
+        Outcome::Bounce => {
+            let (target_v, projectile_v) =
+                outcomes::bounce(constants, target, projectile, impact.normal);
+            return Some(Resolution {
+                survivors: vec![
+                    (
+                        target.entity,
+                        target.pos,
+                        target_v,
+                        target.mass,
+                        target.spin,
+                        target.composition,
+                    ),
+                    (
+                        projectile.entity,
+                        projectile.pos,
+                        projectile_v,
+                        projectile.mass,
+                        projectile.spin,
+                        projectile.composition,
+                    ),
+                ],
+                fragments: vec![],
+            });
+        }
+        Outcome::PartialAccretion { largest_remnant }
+        | Outcome::Fragmentation { largest_remnant } => largest_remnant,
+    };
+
+    let (total_mass, new_v, center_of_mass, _, composition) = group.merged(&constants.densities);
+    let density = composition.density(&constants.densities);
+    let fragments = outcomes::fragment_masses(constants, total_mass - largest_remnant);
+    if fragments.is_empty() {
+        return Some(Resolution::merge(&constants.densities, group));
+    }
+
+    // Take the remnant's mass as whatever the fragments leave so that mass is
+    // conserved exactly.
+    let remnant = total_mass - fragments.iter().copied().sum::<Mass>();
+
+    let mut bodies = outcomes::scatter_fragments(
+        center_of_mass,
+        new_v,
+        impact.normal,
+        impact.escape_speed,
+        remnant,
+        &fragments,
+        density,
+        twist,
+    )
+    .into_iter();
+
+    let (pos, vel, mass) = bodies.next()?;
+    let fragments = bodies
+        .map(|(pos, vel, mass)| (pos, vel, mass, composition))
+        .collect::<Vec<_>>();
+
+    // The remnant's spin takes up whatever angular momentum the fragments
+    // don't carry away.
+    let carried_away = fragments
+        .iter()
+        .map(|&(f_pos, f_vel, f_mass, _)| {
+            f_mass.angular_momentum(Position(f_pos - center_of_mass), f_vel - new_v)
+        })
+        .sum::<AngularMomentum>();
+    let spin = Spin::with_angular_momentum(
+        mass,
+        radius_from_mass(mass, density),
+        group.angular_momentum_about(center_of_mass, new_v) - carried_away,
+    );
+
+    Some(Resolution {
+        survivors: vec![(target.entity, pos, vel, mass, spin, composition)],
+        fragments,
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
 fn collision_resolution_system(
     mut commands: Commands,
     mut collision_groups: ResMut<CollisionGroups>,
     mut q_planets: Query<CollisionResolutionPlanetsData, With<Planet>>,
     mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut conservation: ResMut<Conservation>,
+    mut rng: ResMut<SimRng>,
+    mut stars: Query<(&mut Star, &mut StellarAccretion)>,
+    constants: Res<Constants>,
 ) {
     let mut new_phys_state = HashMap::new();
 
     for group in collision_groups.map.values() {
-        let total_mass = group.iter_all_planets().map(|p| p.mass).sum::<Mass>();
+        let twist = rng.0.gen_range(0.0..consts::TAU);
 
-        let total_momentum = group
+        // Whatever hits a star is swallowed whole, however it hits it.
+        let star = group
             .iter_all_planets()
-            .map(|p| p.mass * p.vel)
-            .sum::<Momentum>();
+            .filter(|p| stars.contains(p.entity))
+            .max_by(|a, b| a.mass.0.total_cmp(&b.mass.0));
+
+        let resolution = match star {
+            Some(star) => Some(Resolution::swallow(
+                &constants.densities,
+                group,
+                star.entity,
+            )),
+            None => resolve(&constants, group, twist),
+        };
+        let Some(resolution) = resolution else {
+            continue;
+        };
 
-        let center_of_mass = group
+        if let Some(star) = star {
+            let swallowed_stars = group
+                .iter_all_planets()
+                .filter(|p| p.entity != star.entity)
+                .filter_map(|p| Some((p.mass, *stars.get(p.entity).ok()?.0)))
+                .collect::<Vec<_>>();
+
+            if let Ok((mut star_data, mut accretion)) = stars.get_mut(star.entity) {
+                let (_, _, _, after, ..) = resolution.survivors[0];
+                let swallowed = group.members.len();
+                accrete_onto_star(
+                    &mut star_data,
+                    &mut accretion,
+                    star.mass,
+                    after,
+                    &swallowed_stars,
+                    swallowed,
+                );
+            }
+        }
+
+        let before = group
             .iter_all_planets()
-            .map(|g| g.pos * g.mass)
-            .sum::<Moment>()
-            / total_mass;
+            .map(|p| (p.pos, p.vel, p.mass, p.spin))
+            .collect::<Vec<_>>();
+        let after = resolution
+            .survivors
+            .iter()
+            .map(|&(_, pos, vel, mass, spin, _)| (pos, vel, mass, spin))
+            .chain(
+                resolution
+                    .fragments
+                    .iter()
+                    .map(|&(pos, vel, mass, composition)| {
+                        let radius =
+                            radius_from_mass(mass, composition.density(&constants.densities));
+                        (pos, vel, mass, Spin::at_rest(mass, radius))
+                    }),
+            )
+            .collect::<Vec<_>>();
+        let (energy, angular_momentum) = diagnostics::collision_change(&constants, &before, &after);
+        conservation.merge_energy += energy;
+        conservation.merge_angular_momentum += angular_momentum;
 
-        let new_v = total_momentum / total_mass;
-        new_phys_state.insert(group.largest.entity, (total_mass, new_v, center_of_mass));
+        // Despawn every member of the group which didn't survive.
+        for planet in group.iter_all_planets() {
+            if !resolution.survivors.iter().any(|s| s.0 == planet.entity) {
+                commands.entity(planet.entity).despawn_recursive();
+            }
+        }
+
+        for &(pos, vel, mass, composition) in &resolution.fragments {
+            let pos = pos + vel * group.remaining;
+            spawn_planet(
+                &mut commands,
+                &mut meshes,
+                &mut materials,
+                &constants.densities,
+                pos,
+                vel,
+                mass,
+                composition,
+            )
+            .insert(Fragment);
+        }
 
-        // Despawn all the group members (excluding `largest`).
-        for planet in &group.members {
-            commands.entity(planet.entity).despawn_recursive();
+        // Everything which came out of the collision coasts from the contact
+        // positions for the rest of the substep.
+        for (entity, pos, vel, mass, spin, composition) in resolution.survivors {
+            let pos = pos + vel * group.remaining;
+            new_phys_state.insert(entity, (mass, vel, pos, spin, composition));
         }
     }
 
-    for (e, mut mesh, mut rad, mut vel, mut mass, mut tsf) in q_planets.iter_mut() {
-        if let Some((new_m, new_v, center_of_mass)) = new_phys_state.get(&e) {
-            *vel = *new_v;
-            *mass = *new_m;
-            *rad = radius_from_mass(*mass);
-            tsf.translation = *center_of_mass;
+    for (e, mut rad, mut vel, mut mass, mut pos, mut spin, mut composition) in q_planets.iter_mut()
+    {
+        if let Some(&(new_m, new_v, new_pos, new_spin, new_composition)) = new_phys_state.get(&e) {
+            *vel = new_v;
+            pos.0 = new_pos;
+            *spin = new_spin;
 
-            *mesh = meshes.set(
-                mesh.as_ref(),
-                UVSphere {
-                    radius: rad.0,
-                    ..default()
-                }
-                .try_into()
-                .unwrap(),
-            );
+            if *composition != new_composition {
+                *composition = new_composition;
+            }
+
+            let new_rad = radius_from_mass(new_m, composition.density(&constants.densities));
+            if *mass != new_m || rad.0 != new_rad.0 {
+                *mass = new_m;
+                *rad = new_rad;
+            }
         }
     }
 
     collision_groups.map.clear();
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::components::from_f32;
+
+    use super::*;
+
+    fn planet(id: u32, x: f32, mass: f32, vel: f32) -> PlanetInfo {
+        PlanetInfo {
+            entity: Entity::from_raw(id),
+            mass: Mass(from_f32(mass)),
+            vel: Velocity(RealVec3::new(0.0, from_f32(vel), 0.0)),
+            pos: RealVec3::new(from_f32(x), 0.0, 0.0),
+            start: RealVec3::new(from_f32(x), 0.0, 0.0),
+            radius: Radius(1.0),
+            spin: Spin::at_rest(Mass(from_f32(mass)), Radius(1.0)),
+            composition: Composition::default(),
+        }
+    }
+
+    fn entities(group: &CollisionGroup) -> Vec<u32> {
+        let mut ids = group
+            .iter_all_planets()
+            .map(|p| p.entity.index())
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids
+    }
+
+    fn assert_conserves(planets: &[(f32, f32)], group: &CollisionGroup) {
+        let (mass, vel, _, _, _) = group.merged(&Densities::default());
+        let total_mass = planets.iter().map(|&(m, _)| m).sum::<f32>();
+        let total_momentum = planets.iter().map(|&(m, v)| m * v).sum::<f32>();
+
+        assert!((mass.0 - from_f32(total_mass)).abs() < 1e-5);
+        assert!(((mass * vel).0.y - from_f32(total_momentum)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn three_body_chain_merges_once() {
+        // 0 touches 1 and 1 touches 2, but 0 and 2 are too far apart to touch.
+        let groups = find_collision_groups(
+            vec![
+                planet(0, 0.0, 3.0, 1.0),
+                planet(1, 1.5, 1.0, -2.0),
+                planet(2, 3.0, 5.0, 0.5),
+            ],
+            Time(1.0),
+        );
+
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].largest.entity.index(), 2);
+        assert_eq!(entities(&groups[0]), [0, 1, 2]);
+        assert_conserves(&[(3.0, 1.0), (1.0, -2.0), (5.0, 0.5)], &groups[0]);
+    }
+
+    #[test]
+    fn three_body_pileup_merges_once() {
+        let groups = find_collision_groups(
+            vec![
+                planet(0, 0.0, 2.0, 1.0),
+                planet(1, 0.5, 4.0, 0.0),
+                planet(2, 1.0, 1.0, -3.0),
+            ],
+            Time(1.0),
+        );
+
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].largest.entity.index(), 1);
+        assert_eq!(groups[0].members.len(), 2);
+        assert_conserves(&[(2.0, 1.0), (4.0, 0.0), (1.0, -3.0)], &groups[0]);
+    }
+
+    #[test]
+    fn four_body_chain_merges_once() {
+        // Two pairs bridged by the middle overlap: the larger body of each pair
+        // would have led its own group under pairwise grouping.
+        let groups = find_collision_groups(
+            vec![
+                planet(0, 0.0, 8.0, 1.0),
+                planet(1, 1.5, 1.0, 2.0),
+                planet(2, 3.0, 2.0, -1.0),
+                planet(3, 4.5, 6.0, 0.25),
+            ],
+            Time(1.0),
+        );
+
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].largest.entity.index(), 0);
+        assert_eq!(entities(&groups[0]), [0, 1, 2, 3]);
+        assert_conserves(
+            &[(8.0, 1.0), (1.0, 2.0), (2.0, -1.0), (6.0, 0.25)],
+            &groups[0],
+        );
+    }
+
+    #[test]
+    fn fast_body_does_not_tunnel() {
+        // A small body crosses a large one within a single step, starting and
+        // ending well clear of it.
+        let mut bullet = planet(1, 10.0, 0.1, 0.0);
+        bullet.start = RealVec3::new(-10.0, 0.0, 0.0);
+        bullet.vel = Velocity(RealVec3::new(20.0, 0.0, 0.0));
+
+        let groups = find_collision_groups(vec![planet(0, 0.0, 5.0, 0.0), bullet], Time(1.0));
+
+        assert_eq!(groups.len(), 1);
+        let group = &groups[0];
+        assert_eq!(group.largest.entity.index(), 0);
+
+        // The radii sum to 2, so they touch 8 units into the 20 unit path.
+        assert!((group.members[0].pos.x + 2.0).abs() < 1e-4);
+        assert!((group.remaining.0 - 0.6).abs() < 1e-4);
+    }
+
+    #[test]
+    fn merge_keeps_angular_momentum_as_spin() {
+        // Two bodies passing each other in opposite directions, offset along x.
+        let group = CollisionGroup {
+            largest: planet(0, 0.0, 3.0, 2.0),
+            members: vec![planet(1, 1.5, 1.0, -1.0)],
+            remaining: Time::ZERO,
+        };
+
+        let before = group
+            .iter_all_planets()
+            .map(|p| p.mass.angular_momentum(Position(p.pos), p.vel) + p.spin.angular_momentum())
+            .sum::<AngularMomentum>();
+
+        let (mass, vel, center_of_mass, spin, _) = group.merged(&Densities::default());
+        let after = mass.angular_momentum(Position(center_of_mass), vel) + spin.angular_momentum();
+
+        assert!(spin.angular_velocity.length() > 0.0);
+        assert!((after - before).0.length() < 1e-4);
+    }
+
+    #[test]
+    fn separate_pairs_merge_separately() {
+        let groups = find_collision_groups(
+            vec![
+                planet(0, 0.0, 1.0, 0.0),
+                planet(1, 1.5, 2.0, 0.0),
+                planet(2, 10.0, 3.0, 0.0),
+                planet(3, 11.5, 4.0, 0.0),
+                planet(4, 20.0, 5.0, 0.0),
+            ],
+            Time(1.0),
+        );
+
+        assert_eq!(groups.len(), 2);
+        assert_eq!(entities(&groups[0]), [0, 1]);
+        assert_eq!(entities(&groups[1]), [2, 3]);
+    }
+
+    #[test]
+    fn hit_and_run_separates_the_bodies() {
+        let constants = Constants {
+            collision_model: CollisionModel::LeinhardtStewart,
+            ..default()
+        };
+        let density = Composition::default().density(&constants.densities);
+        let target = PlanetInfo {
+            entity: Entity::from_raw(0),
+            mass: Mass(100.0),
+            vel: Velocity::ZERO,
+            pos: RealVec3::ZERO,
+            start: RealVec3::ZERO,
+            radius: radius_from_mass(Mass(100.0), density),
+            spin: Spin::at_rest(Mass(100.0), radius_from_mass(Mass(100.0), density)),
+            composition: Composition::default(),
+        };
+        let mut projectile = PlanetInfo {
+            entity: Entity::from_raw(1),
+            mass: Mass(10.0),
+            vel: Velocity(RealVec3::new(-50.0, 0.0, 0.0)),
+            pos: RealVec3::ZERO,
+            start: RealVec3::ZERO,
+            radius: radius_from_mass(Mass(10.0), density),
+            spin: Spin::at_rest(Mass(10.0), radius_from_mass(Mass(10.0), density)),
+            composition: Composition::default(),
+        };
+        let dist = 0.99 * (target.radius + projectile.radius).0;
+        let angle = consts::FRAC_PI_3;
+        projectile.pos = dist * RealVec3::new(angle.cos(), angle.sin(), 0.0);
+        projectile.start = projectile.pos;
+        assert_eq!(
+            outcomes::classify(&constants, &target, &projectile),
+            Some(Outcome::HitAndRun)
+        );
+
+        let group = CollisionGroup {
+            largest: target,
+            members: vec![projectile],
+            remaining: Time::ZERO,
+        };
+        let resolution = resolve(&constants, &group, 0.0).unwrap();
+
+        let [(target_id, target_pos, _, target_mass, ..), (projectile_id, ..)] =
+            resolution.survivors[..]
+        else {
+            panic!("both bodies should survive");
+        };
+        assert_eq!(target_id, group.largest.entity);
+        assert_eq!(projectile_id, group.members[0].entity);
+        assert_eq!(target_mass, group.largest.mass);
+
+        let after = resolution.survivors[1..]
+            .iter()
+            .map(|&(_, pos, vel, mass, ..)| (pos, vel, mass))
+            .chain(
+                resolution
+                    .fragments
+                    .iter()
+                    .map(|&(pos, vel, mass, _)| (pos, vel, mass)),
+            )
+            .collect::<Vec<_>>();
+        assert!(
+            !resolution.fragments.is_empty(),
+            "the projectile should be eroded"
+        );
+        for &(pos, _, mass) in &after {
+            let touching = (group.largest.radius + radius_from_mass(mass, density)).0;
+            assert!(pos.distance(target_pos) > touching);
+        }
+
+        let (mass, vel, center_of_mass, ..) = group.merged(&constants.densities);
+        let total_mass = after.iter().map(|&(_, _, m)| m).sum::<Mass>() + target_mass;
+        let momentum = after
+            .iter()
+            .map(|&(_, vel, mass)| mass * vel)
+            .sum::<Momentum>();
+        let moment = after
+            .iter()
+            .map(|&(pos, _, mass)| pos * mass)
+            .sum::<Moment>()
+            + target_pos * target_mass;
+        assert!((total_mass - mass).0.abs() < 1e-4);
+        assert!((momentum - mass * vel).0.length() < 1e-3);
+        assert!((moment / total_mass - center_of_mass).length() < 1e-4);
+    }
+
+    #[test]
+    fn swallowing_a_dense_planet_grows_a_star() {
+        let densities = Densities::default();
+        let mut star = planet(0, 0.0, 1000.0, 0.0);
+        star.composition = Composition::GAS;
+        let mut rock = planet(1, 1.5, 100.0, 0.0);
+        rock.composition = Composition::EARTH_LIKE;
+
+        let radius_before = radius_from_mass(star.mass, Composition::GAS.density(&densities));
+        let group = CollisionGroup {
+            largest: star,
+            members: vec![rock],
+            remaining: Time::ZERO,
+        };
+
+        let resolution = Resolution::swallow(&densities, &group, Entity::from_raw(0));
+        let (entity, _, _, mass, _, composition) = resolution.survivors[0];
+
+        assert_eq!(entity.index(), 0);
+        assert_eq!(composition, Composition::GAS);
+        assert!((mass.0 - 1100.0).abs() < 1e-3);
+        assert!(radius_from_mass(mass, composition.density(&densities)).0 > radius_before.0);
+    }
+}
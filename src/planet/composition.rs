@@ -0,0 +1,242 @@
+//! What a body is made of, which sets how dense it is and what color it's
+//! drawn in.
+
+use bevy::prelude::*;
+use bevy_inspector_egui::{prelude::ReflectInspectorOptions, InspectorOptions};
+use rand::Rng;
+
+use crate::components::{to_f32, Mass, Real};
+
+/// Temperature, in kelvin, below which water ice condenses in the disk.
+pub const ICE_CONDENSATION_TEMPERATURE: Real = 170.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Material {
+    /// Silicates.
+    Rock,
+    /// Iron and nickel.
+    Metal,
+    /// Water, ammonia and methane ices.
+    Ice,
+    /// Hydrogen and helium.
+    Gas,
+}
+
+impl Material {
+    pub const ALL: [Self; 4] = [Self::Rock, Self::Metal, Self::Ice, Self::Gas];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Rock => "Rock",
+            Self::Metal => "Metal",
+            Self::Ice => "Ice",
+            Self::Gas => "Gas",
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            Self::Rock => Color::rgb(0.55, 0.42, 0.33),
+            Self::Metal => Color::rgb(0.62, 0.63, 0.68),
+            Self::Ice => Color::rgb(0.78, 0.88, 0.97),
+            Self::Gas => Color::rgb(0.87, 0.72, 0.5),
+        }
+    }
+}
+
+/// Mass fraction of each [`Material`] in a body. The fractions sum to one.
+#[derive(Component, Reflect, InspectorOptions, Debug, Clone, Copy, PartialEq)]
+#[reflect(InspectorOptions)]
+pub struct Composition {
+    pub rock: Real,
+    pub metal: Real,
+    pub ice: Real,
+    pub gas: Real,
+}
+
+impl Default for Composition {
+    fn default() -> Self {
+        Self::EARTH_LIKE
+    }
+}
+
+impl Composition {
+    /// Two parts rock to one part metal, like the Earth.
+    pub const EARTH_LIKE: Self = Self {
+        rock: 0.67,
+        metal: 0.33,
+        ice: 0.0,
+        gas: 0.0,
+    };
+
+    /// Nothing but hydrogen and helium, like a star.
+    pub const GAS: Self = Self {
+        rock: 0.0,
+        metal: 0.0,
+        ice: 0.0,
+        gas: 1.0,
+    };
+
+    /// A composition with the given relative amounts of each material. Falls
+    /// back to the default if there's nothing at all.
+    pub fn from_parts(rock: Real, metal: Real, ice: Real, gas: Real) -> Self {
+        let total = rock + metal + ice + gas;
+        if total <= 0.0 {
+            return Self::default();
+        }
+
+        Self {
+            rock: rock / total,
+            metal: metal / total,
+            ice: ice / total,
+            gas: gas / total,
+        }
+    }
+
+    /// A random mix of the solids which condense out of the disk at
+    /// `temperature`: rock and metal everywhere, and about as much ice again
+    /// beyond the snow line.
+    pub fn condensed_at(temperature: Real, rng: &mut impl Rng) -> Self {
+        let ice = if temperature < ICE_CONDENSATION_TEMPERATURE {
+            rng.gen_range(0.6..1.4)
+        } else {
+            0.0
+        };
+
+        Self::from_parts(rng.gen_range(0.5..0.8), rng.gen_range(0.2..0.5), ice, 0.0)
+    }
+
+    /// The typical mix of solids at `temperature`, as in [`Self::condensed_at`]
+    /// but without the scatter.
+    pub fn solids_at(temperature: Real) -> Self {
+        let ice = if temperature < ICE_CONDENSATION_TEMPERATURE {
+            1.0
+        } else {
+            0.0
+        };
+
+        Self::from_parts(0.67, 0.33, ice, 0.0)
+    }
+
+    pub fn fraction(&self, material: Material) -> Real {
+        match material {
+            Material::Rock => self.rock,
+            Material::Metal => self.metal,
+            Material::Ice => self.ice,
+            Material::Gas => self.gas,
+        }
+    }
+
+    /// Composition of the body made by combining `parts`, each weighted by its
+    /// mass.
+    pub fn mix(parts: impl IntoIterator<Item = (Mass, Composition)>) -> Self {
+        let mut total = Self {
+            rock: 0.0,
+            metal: 0.0,
+            ice: 0.0,
+            gas: 0.0,
+        };
+
+        for (mass, part) in parts {
+            total.rock += mass.0 * part.rock;
+            total.metal += mass.0 * part.metal;
+            total.ice += mass.0 * part.ice;
+            total.gas += mass.0 * part.gas;
+        }
+
+        Self::from_parts(total.rock, total.metal, total.ice, total.gas)
+    }
+
+    /// Bulk density, in g/cm³, taking the volumes of the materials to add up.
+    pub fn density(&self, densities: &Densities) -> Real {
+        let specific_volume = Material::ALL
+            .into_iter()
+            .map(|material| self.fraction(material) / densities.get(material))
+            .sum::<Real>();
+        1.0 / specific_volume
+    }
+
+    /// Each material's color, weighted by how much of it there is.
+    pub fn color(&self) -> Color {
+        let [r, g, b] = Material::ALL.into_iter().fold([0.0; 3], |rgb, material| {
+            let [r, g, b, _] = material.color().as_rgba_f32();
+            let fraction = to_f32(self.fraction(material));
+            [
+                rgb[0] + fraction * r,
+                rgb[1] + fraction * g,
+                rgb[2] + fraction * b,
+            ]
+        });
+        Color::rgb(r, g, b)
+    }
+}
+
+/// Uncompressed density of each [`Material`], in g/cm³. They are applied as
+/// bodies are spawned or collide; changing them doesn't resize existing bodies.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Densities {
+    pub rock: Real,
+    pub metal: Real,
+    pub ice: Real,
+    /// Gas is taken at the mean density of the Sun rather than that of free
+    /// gas, so that stars and gas giants come out a sensible size.
+    pub gas: Real,
+}
+
+impl Default for Densities {
+    fn default() -> Self {
+        Self {
+            rock: 3.3,
+            metal: 7.9,
+            ice: 0.94,
+            gas: 1.4,
+        }
+    }
+}
+
+impl Densities {
+    pub fn get(&self, material: Material) -> Real {
+        match material {
+            Material::Rock => self.rock,
+            Material::Metal => self.metal,
+            Material::Ice => self.ice,
+            Material::Gas => self.gas,
+        }
+    }
+
+    pub fn get_mut(&mut self, material: Material) -> &mut Real {
+        match material {
+            Material::Rock => &mut self.rock,
+            Material::Metal => &mut self.metal,
+            Material::Ice => &mut self.ice,
+            Material::Gas => &mut self.gas,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mixing_conserves_each_material() {
+        let a = (Mass(3.0), Composition::EARTH_LIKE);
+        let b = (Mass(1.0), Composition::from_parts(0.0, 0.0, 1.0, 1.0));
+        let mixed = Composition::mix([a, b]);
+
+        assert!((mixed.rock - 0.75 * 0.67).abs() < 1e-5);
+        assert!((mixed.metal - 0.75 * 0.33).abs() < 1e-5);
+        assert!((mixed.ice - 0.125).abs() < 1e-5);
+        assert!((mixed.gas - 0.125).abs() < 1e-5);
+    }
+
+    #[test]
+    fn density_adds_volumes() {
+        let densities = Densities::default();
+        let half_and_half = Composition::from_parts(1.0, 0.0, 1.0, 0.0);
+
+        // 1 g of each takes up 1/3.3 + 1/0.94 cm³.
+        let expected = 2.0 / (1.0 / densities.rock + 1.0 / densities.ice);
+        assert!((half_and_half.density(&densities) - expected).abs() < 1e-5);
+    }
+}
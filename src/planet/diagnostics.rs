@@ -0,0 +1,243 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::components::{
+    AngularMomentum, Energy, Mass, Momentum, Position, Real, RealVec3, Spin, Velocity,
+};
+
+use super::{
+    collisions::Fragment,
+    gravity,
+    simulation::{PhysicsSet, SimClock, SimulationStep},
+    Constants, Planet,
+};
+
+/// Tracks how well the simulation conserves energy, linear momentum and
+/// angular momentum.
+pub struct DiagnosticsPlugin;
+
+impl Plugin for DiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app // <no autoformat>
+            .init_resource::<Conservation>()
+            .add_systems(
+                SimulationStep,
+                conservation_system.in_set(PhysicsSet::Diagnostics),
+            );
+    }
+}
+
+/// Only every this many steps is added to [`Conservation::history`].
+const SAMPLE_EVERY: u64 = 15;
+const HISTORY_LEN: usize = 4096;
+
+#[derive(Clone, Copy, Default)]
+pub struct Totals {
+    pub kinetic: Energy,
+    pub potential: Energy,
+    pub momentum: Momentum,
+    pub angular_momentum: AngularMomentum,
+}
+
+impl Totals {
+    pub fn energy(&self) -> Energy {
+        self.kinetic + self.potential
+    }
+}
+
+/// Relative drift of each conserved quantity from the baseline.
+#[derive(Clone, Copy)]
+pub struct DriftSample {
+    pub time: Real,
+    pub energy: Real,
+    pub momentum: Real,
+    pub angular_momentum: Real,
+}
+
+#[derive(Resource, Default)]
+pub struct Conservation {
+    /// Totals at t=0, or whenever a planet was last added.
+    pub baseline: Option<Totals>,
+    pub current: Totals,
+    /// Change in total energy caused by resolving collisions since the
+    /// baseline. Collisions are inelastic, so this isn't integrator error and
+    /// is taken out before computing the energy drift.
+    pub merge_energy: Energy,
+    /// Change in total angular momentum caused by resolving collisions since
+    /// the baseline.
+    pub merge_angular_momentum: AngularMomentum,
+    /// Energy the bodies have taken from the gas disk since the baseline,
+    /// through the forces accumulated into `Force` on top of gravity and the
+    /// mass they accrete from it. The gas isn't one of the bodies, so this is
+    /// taken out of the drift too.
+    pub disk_energy: Energy,
+    /// Momentum the bodies have taken from the gas disk since the baseline.
+    pub disk_momentum: Momentum,
+    /// Angular momentum the bodies have taken from the gas disk since the
+    /// baseline.
+    pub disk_angular_momentum: AngularMomentum,
+    pub history: VecDeque<DriftSample>,
+    /// Sum of the magnitudes of every body's momentum.
+    momentum_scale: Real,
+}
+
+impl Conservation {
+    pub fn reset_baseline(&mut self) {
+        self.baseline = None;
+        self.merge_energy = Energy::ZERO;
+        self.merge_angular_momentum = AngularMomentum::ZERO;
+        self.disk_energy = Energy::ZERO;
+        self.disk_momentum = Momentum::ZERO;
+        self.disk_angular_momentum = AngularMomentum::ZERO;
+        self.history.clear();
+    }
+
+    /// Relative drift of each quantity, with merge losses and what was
+    /// exchanged with the disk accounted for.
+    pub fn drift(&self, time: Real) -> Option<DriftSample> {
+        let base = self.baseline?;
+        let now = self.current;
+
+        // A lone star, or bodies at rest, can start with nothing to measure
+        // against, so keep the scales away from zero.
+        let energy_scale = base.energy().0.abs().max(Real::EPSILON);
+        let energy =
+            (now.energy() - self.merge_energy - self.disk_energy - base.energy()).0 / energy_scale;
+
+        // Total momentum is often close to zero, so measure its change against
+        // the total momentum the bodies would have if all moved the same way.
+        let momentum_scale = self.momentum_scale.max(Real::EPSILON);
+        let momentum = (now.momentum - self.disk_momentum - base.momentum)
+            .0
+            .length()
+            / momentum_scale;
+
+        let angular_momentum_scale = base.angular_momentum.0.length().max(Real::EPSILON);
+        let angular_momentum = (now.angular_momentum
+            - self.merge_angular_momentum
+            - self.disk_angular_momentum
+            - base.angular_momentum)
+            .0
+            .length()
+            / angular_momentum_scale;
+
+        Some(DriftSample {
+            time,
+            energy,
+            momentum,
+            angular_momentum,
+        })
+    }
+}
+
+fn conservation_system(
+    mut conservation: ResMut<Conservation>,
+    planets: Query<(&Position, &Velocity, &Mass, &Spin), With<Planet>>,
+    added: Query<(), (Added<Planet>, Without<Fragment>)>,
+    constants: Res<Constants>,
+    clock: Res<SimClock>,
+) {
+    if !added.is_empty() {
+        conservation.reset_baseline();
+    }
+
+    let mut totals = Totals::default();
+    let mut momentum_scale = 0.0;
+    let mut masses = Vec::with_capacity(planets.iter().len());
+
+    for (&pos, &vel, &mass, &spin) in &planets {
+        totals.kinetic += mass.kinetic_energy(vel) + spin.kinetic_energy();
+        totals.momentum += mass * vel;
+        totals.angular_momentum += mass.angular_momentum(pos, vel) + spin.angular_momentum();
+        momentum_scale += (mass * vel).0.length();
+        masses.push((pos.0, mass));
+    }
+
+    totals.potential = gravity::total_potential(&constants, &masses);
+
+    conservation.current = totals;
+    conservation.momentum_scale = momentum_scale;
+    if conservation.baseline.is_none() && !masses.is_empty() {
+        conservation.baseline = Some(totals);
+    }
+
+    if clock.steps.is_multiple_of(SAMPLE_EVERY) {
+        let time = clock.elapsed.0;
+        if let Some(sample) = conservation.drift(time) {
+            if conservation.history.len() == HISTORY_LEN {
+                conservation.history.pop_front();
+            }
+            conservation.history.push_back(sample);
+        }
+    }
+}
+
+/// How much resolving a collision which turns the bodies `before` into the
+/// bodies `after` changes the total energy and angular momentum, for
+/// [`Conservation::merge_energy`] and [`Conservation::merge_angular_momentum`].
+/// The small change in potential energy between the colliding bodies and
+/// everything else is ignored.
+pub fn collision_change(
+    constants: &Constants,
+    before: &[(RealVec3, Velocity, Mass, Spin)],
+    after: &[(RealVec3, Velocity, Mass, Spin)],
+) -> (Energy, AngularMomentum) {
+    let totals = |bodies: &[(RealVec3, Velocity, Mass, Spin)]| {
+        let kinetic = bodies
+            .iter()
+            .map(|&(_, vel, mass, spin)| mass.kinetic_energy(vel) + spin.kinetic_energy())
+            .sum::<Energy>();
+
+        let positions = bodies
+            .iter()
+            .map(|&(pos, _, mass, _)| (pos, mass))
+            .collect::<Vec<_>>();
+        let internal_potential = gravity::total_potential(constants, &positions);
+
+        let angular_momentum = bodies
+            .iter()
+            .map(|&(pos, vel, mass, spin)| {
+                mass.angular_momentum(Position(pos), vel) + spin.angular_momentum()
+            })
+            .sum::<AngularMomentum>();
+
+        (kinetic + internal_potential, angular_momentum)
+    };
+
+    let (energy_before, angular_momentum_before) = totals(before);
+    let (energy_after, angular_momentum_after) = totals(after);
+
+    (
+        energy_after - energy_before,
+        angular_momentum_after - angular_momentum_before,
+    )
+}
+
+/// How much a body going from `before` to `after` by accreting mass from the
+/// disk changes the total energy, momentum and angular momentum, for the
+/// `disk_*` totals of [`Conservation`]. Unlike in [`collision_change`], the
+/// mass is new, so its potential energy against the `others` counts too.
+pub fn accretion_change(
+    constants: &Constants,
+    others: impl IntoIterator<Item = (RealVec3, Mass)>,
+    before: (RealVec3, Velocity, Mass, Spin),
+    after: (RealVec3, Velocity, Mass, Spin),
+) -> (Energy, Momentum, AngularMomentum) {
+    let (pos, vel, mass, _) = after;
+    let accreted = mass - before.2;
+
+    let (energy, angular_momentum) = collision_change(constants, &[before], &[after]);
+    let potential = others
+        .into_iter()
+        .map(|(other_pos, other_mass)| {
+            gravity::potential(constants, accreted.0, other_mass.0, pos.distance(other_pos))
+        })
+        .sum::<Energy>();
+
+    (
+        energy + potential,
+        mass * vel - before.2 * before.1,
+        angular_momentum,
+    )
+}
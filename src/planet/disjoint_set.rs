@@ -0,0 +1,69 @@
+/// Union-find over the indices `0..len`, with path compression and union by
+/// size.
+pub struct DisjointSet {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+}
+
+impl DisjointSet {
+    pub fn new(len: usize) -> Self {
+        Self {
+            parent: (0..len).collect(),
+            size: vec![1; len],
+        }
+    }
+
+    /// The representative of the set containing `i`.
+    pub fn find(&mut self, i: usize) -> usize {
+        let mut root = i;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+
+        let mut i = i;
+        while self.parent[i] != root {
+            let next = self.parent[i];
+            self.parent[i] = root;
+            i = next;
+        }
+
+        root
+    }
+
+    /// Merges the sets containing `a` and `b`.
+    pub fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
+
+        let (big, small) = if self.size[a] >= self.size[b] {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        self.parent[small] = big;
+        self.size[big] += self.size[small];
+    }
+
+    /// Every set with more than one element, each listed in increasing index
+    /// order. The sets themselves are ordered by their smallest element.
+    pub fn groups(&mut self) -> Vec<Vec<usize>> {
+        let mut by_root: Vec<Option<usize>> = vec![None; self.parent.len()];
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+
+        for i in 0..self.parent.len() {
+            let root = self.find(i);
+            if self.size[root] < 2 {
+                continue;
+            }
+            let idx = *by_root[root].get_or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            });
+            groups[idx].push(i);
+        }
+
+        groups
+    }
+}
@@ -0,0 +1,133 @@
+//! The disk's temperature, set by the stars heating it.
+
+use bevy::prelude::*;
+use bevy_inspector_egui::{prelude::ReflectInspectorOptions, InspectorOptions};
+
+use crate::components::{to_f32, to_vec3, Position, Real, RealVec3};
+
+use super::composition::ICE_CONDENSATION_TEMPERATURE;
+
+/// Length of an astronomical unit in simulation units.
+pub const AU: Real = 100.0;
+
+/// Temperature, in kelvin, one AU from a star of one solar luminosity.
+const TEMPERATURE_AT_1_AU: Real = 280.0;
+
+/// A body that heats the disk and lights the scene.
+#[derive(Component, Reflect, InspectorOptions, Debug, Clone, Copy)]
+#[reflect(InspectorOptions)]
+pub struct Star {
+    /// In solar luminosities.
+    #[inspector(min = 0.0)]
+    pub luminosity: Real,
+    /// Color of the star's light and surface.
+    pub color: Color,
+}
+
+impl Default for Star {
+    fn default() -> Self {
+        Self {
+            luminosity: 1.0,
+            color: Color::rgb(1.0, 0.8, 0.55),
+        }
+    }
+}
+
+impl Star {
+    /// Temperature, in kelvin, of an optically thin disk `dist` from the star,
+    /// falling off as T ∝ L^¼ r^-½ (Hayashi 1981).
+    pub fn temperature_at(&self, dist: Real) -> Real {
+        TEMPERATURE_AT_1_AU * self.luminosity.powf(0.25) * (dist / AU).powf(-0.5)
+    }
+
+    /// Distance from the star beyond which ices condense.
+    pub fn snow_line(&self) -> Real {
+        AU * self.luminosity.sqrt() * (TEMPERATURE_AT_1_AU / ICE_CONDENSATION_TEMPERATURE).powi(2)
+    }
+}
+
+/// Temperature of the disk at `pos` heated by every one of `stars`. The heat
+/// of several stars adds, so the fourth powers of their temperatures do.
+pub fn temperature<'a>(
+    stars: impl IntoIterator<Item = (&'a Star, &'a Position)>,
+    pos: RealVec3,
+) -> Real {
+    stars
+        .into_iter()
+        .map(|(star, star_pos)| star.temperature_at(star_pos.0.distance(pos)).powi(4))
+        .sum::<Real>()
+        .powf(0.25)
+}
+
+#[derive(Resource)]
+pub struct SnowLineOverlay {
+    pub enabled: bool,
+}
+
+impl Default for SnowLineOverlay {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+/// Draws each star's snow line as a ring on the ecliptic.
+pub fn snow_line_overlay_system(
+    overlay: Res<SnowLineOverlay>,
+    stars: Query<(&Star, &Position)>,
+    mut gizmos: Gizmos,
+) {
+    if !overlay.enabled {
+        return;
+    }
+
+    for (star, pos) in &stars {
+        gizmos
+            .circle(
+                to_vec3(pos.0),
+                Vec3::Y,
+                to_f32(star.snow_line()),
+                Color::rgba(0.6, 0.8, 1.0, 0.4),
+            )
+            .segments(128);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::planet::composition::Composition;
+
+    fn star(luminosity: Real) -> Star {
+        Star {
+            luminosity,
+            ..default()
+        }
+    }
+
+    #[test]
+    fn snow_line_is_at_ice_condensation_temperature() {
+        for luminosity in [0.05, 1.0, 3.0, 40.0] {
+            let star = star(luminosity);
+            let temperature = star.temperature_at(star.snow_line());
+            assert!((temperature / ICE_CONDENSATION_TEMPERATURE - 1.0).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn ice_condenses_only_beyond_snow_line() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for luminosity in [0.05, 1.0, 40.0] {
+            let star = star(luminosity);
+            let inside = star.temperature_at(0.99 * star.snow_line());
+            let outside = star.temperature_at(1.01 * star.snow_line());
+
+            for _ in 0..20 {
+                assert_eq!(Composition::condensed_at(inside, &mut rng).ice, 0.0);
+                assert!(Composition::condensed_at(outside, &mut rng).ice > 0.0);
+            }
+        }
+    }
+}
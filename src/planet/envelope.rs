@@ -0,0 +1,251 @@
+//! Core accretion: once a planet's core is massive enough it can no longer
+//! hold up a gas envelope, which then collapses onto it and draws in more gas
+//! from the disk in a runaway (Pollack et al. 1996, Ikoma et al. 2000).
+
+use bevy::prelude::*;
+
+use crate::components::{consts, Mass, Position, Real, Velocity};
+
+use super::{
+    accrete,
+    composition::Composition,
+    diagnostics::Conservation,
+    disk::Star,
+    gas::GasDisk,
+    pebbles::pebble_accretion_system,
+    simulation::{PhysicsSet, SimClock, SimulationStep},
+    stars::orbit_center,
+    AccretingPlanetsData, Constants, Planet,
+};
+
+pub struct EnvelopePlugin;
+
+impl Plugin for EnvelopePlugin {
+    fn build(&self, app: &mut App) {
+        app // <no autoformat>
+            .add_systems(
+                SimulationStep,
+                envelope_accretion_system
+                    .in_set(PhysicsSet::Accretion)
+                    .after(pebble_accretion_system),
+            );
+    }
+}
+
+/// Aspect ratio at which the gap-opening mass is
+/// [`Constants::gap_opening_mass`].
+const GAP_OPENING_ASPECT_RATIO: Real = 0.05;
+
+/// Mass at which a planet clears a gap in the gas and stops accreting it,
+/// where the disk's aspect ratio is `aspect_ratio`.
+pub fn gap_opening_mass(constants: &Constants, aspect_ratio: Real) -> Mass {
+    Mass(constants.gap_opening_mass * (aspect_ratio / GAP_OPENING_ASPECT_RATIO).powi(3))
+}
+
+/// Mass of everything in the planet but its gas.
+pub fn core_mass(mass: Mass, composition: &Composition) -> Mass {
+    Mass(mass.0 * (1.0 - composition.gas))
+}
+
+/// Rate at which a planet of mass `mass` takes on gas if the disk can supply
+/// it: as fast as its envelope can cool and contract.
+fn contraction_rate(constants: &Constants, mass: Mass) -> Real {
+    // The Kelvin-Helmholtz contraction time falls steeply as the planet grows,
+    // t_KH ∝ M^-3 (Ida & Lin 2004).
+    let kelvin_helmholtz_time =
+        constants.envelope_timescale * (mass.0 / constants.critical_core_mass).powi(-3);
+    mass.0 / kelvin_helmholtz_time
+}
+
+/// Most gas the disk of surface density `sigma` can feed a planet of mass
+/// `mass`, `r` from a star of mass `star_mass`, per unit time (Tanigawa &
+/// Watanabe 2002).
+fn supply_rate(
+    constants: &Constants,
+    gas: &GasDisk,
+    star_mass: Mass,
+    mass: Mass,
+    r: Real,
+    sigma: Real,
+) -> Real {
+    let h = gas.aspect_ratio_at(r);
+    let omega = (constants.grav_const * star_mass.0 / r.powi(3)).sqrt();
+    let hill_ratio = (mass.0 / star_mass.0).powf(4.0 / 3.0);
+    0.29 / consts::PI * h.powi(-2) * hill_ratio * sigma * r * r * omega
+}
+
+/// Rate at which a planet takes on gas: as fast as its envelope can contract,
+/// but no faster than the disk can feed it.
+fn envelope_accretion_rate(
+    constants: &Constants,
+    gas: &GasDisk,
+    star_mass: Mass,
+    mass: Mass,
+    r: Real,
+    sigma: Real,
+) -> Real {
+    contraction_rate(constants, mass).min(supply_rate(constants, gas, star_mass, mass, r, sigma))
+}
+
+/// Gas taken on over `dt` by a planet of mass `mass` and the given
+/// composition, `r` from a star of mass `star_mass`. Nothing unless its core
+/// is past [`Constants::critical_core_mass`], and never past the gap-opening
+/// mass.
+fn envelope_growth(
+    constants: &Constants,
+    gas: &GasDisk,
+    star_mass: Mass,
+    mass: Mass,
+    composition: &Composition,
+    r: Real,
+    clock: &SimClock,
+) -> Mass {
+    if core_mass(mass, composition).0 < constants.critical_core_mass {
+        return Mass::ZERO;
+    }
+
+    let limit = gap_opening_mass(constants, gas.aspect_ratio_at(r));
+    let sigma = gas.surface_density_at(r, clock.elapsed);
+    if r == 0.0 || mass.0 >= limit.0 || sigma <= 0.0 {
+        return Mass::ZERO;
+    }
+
+    let rate = envelope_accretion_rate(constants, gas, star_mass, mass, r, sigma);
+    Mass((rate * clock.dt.0).min(limit.0 - mass.0))
+}
+
+/// Grows an envelope on every planet whose core is past
+/// [`Constants::critical_core_mass`], until it opens a gap in the disk.
+///
+/// The gas a planet takes on isn't removed from the [`GasDisk`], so the disk
+/// only runs out through its own exponential decay, never locally.
+fn envelope_accretion_system(
+    mut planets: Query<AccretingPlanetsData, (With<Planet>, Without<Star>)>,
+    stars: Query<(Entity, &Position, &Velocity, &Mass), With<Star>>,
+    mut conservation: ResMut<Conservation>,
+    gas: Res<GasDisk>,
+    constants: Res<Constants>,
+    clock: Res<SimClock>,
+) {
+    if !constants.gas_accretion || !gas.enabled {
+        return;
+    }
+
+    let bodies = stars
+        .iter()
+        .map(|(e, pos, _, &mass)| (e, pos.0, mass))
+        .chain(
+            planets
+                .iter()
+                .map(|(e, pos, _, &mass, ..)| (e, pos.0, mass)),
+        )
+        .collect::<Vec<_>>();
+
+    for (e, pos, vel, mut mass, mut radius, mut composition, mut spin) in &mut planets {
+        let centers = stars.iter().map(|(_, pos, vel, mass)| (pos, vel, mass));
+        let Some(center) = orbit_center(centers, pos.0) else {
+            continue;
+        };
+
+        let r = pos.0.distance(center.pos);
+        let accreted = envelope_growth(
+            &constants,
+            &gas,
+            center.mass,
+            *mass,
+            &composition,
+            r,
+            &clock,
+        );
+        if accreted.0 <= 0.0 {
+            continue;
+        }
+
+        accrete(
+            &constants,
+            &mut conservation,
+            &bodies,
+            (
+                e,
+                pos,
+                vel,
+                &mut mass,
+                &mut radius,
+                &mut composition,
+                &mut spin,
+            ),
+            accreted,
+            Composition::GAS,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{components::Time, planet::disk::AU};
+
+    const STAR_MASS: Mass = Mass(1000.0);
+
+    fn growth(mass: Real, composition: Composition, dt: Real) -> Mass {
+        let clock = SimClock {
+            dt: Time(dt),
+            ..default()
+        };
+        envelope_growth(
+            &Constants::default(),
+            &GasDisk::default(),
+            STAR_MASS,
+            Mass(mass),
+            &composition,
+            AU,
+            &clock,
+        )
+    }
+
+    #[test]
+    fn no_envelope_below_critical_core_mass() {
+        let critical = Constants::default().critical_core_mass;
+
+        assert_eq!(
+            growth(0.99 * critical, Composition::default(), 1e6),
+            Mass::ZERO
+        );
+        assert!(growth(1.01 * critical, Composition::default(), 1.0).0 > 0.0);
+
+        // What counts is the core, not the gas around it.
+        let half_gas = Composition::from_parts(0.5, 0.0, 0.0, 0.5);
+        assert_eq!(growth(1.5 * critical, half_gas, 1e6), Mass::ZERO);
+    }
+
+    #[test]
+    fn growth_stops_at_gap_opening_mass() {
+        let constants = Constants::default();
+        let limit = gap_opening_mass(&constants, GasDisk::default().aspect_ratio_at(AU));
+        assert!(limit.0 > constants.critical_core_mass);
+
+        let grown = growth(0.9 * limit.0, Composition::default(), 1e9);
+        assert!((grown.0 - 0.1 * limit.0).abs() < 1e-4 * limit.0);
+        assert_eq!(growth(limit.0, Composition::default(), 1e9), Mass::ZERO);
+    }
+
+    #[test]
+    fn rate_is_limited_by_contraction_or_supply() {
+        let constants = Constants::default();
+        let gas = GasDisk::default();
+        let mass = Mass(1.5 * constants.critical_core_mass);
+        let rate = |sigma| envelope_accretion_rate(&constants, &gas, STAR_MASS, mass, AU, sigma);
+        let contraction = contraction_rate(&constants, mass);
+        let supply = |sigma| supply_rate(&constants, &gas, STAR_MASS, mass, AU, sigma);
+
+        // A thin disk can't keep up with the envelope contracting...
+        let thin = 1e-6;
+        assert!(supply(thin) < contraction);
+        assert_eq!(rate(thin), supply(thin));
+
+        // ...but a thick one can.
+        let thick = 1e3;
+        assert!(supply(thick) > contraction);
+        assert_eq!(rate(thick), contraction);
+    }
+}
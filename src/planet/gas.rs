@@ -0,0 +1,466 @@
+//! The disk's gas, which doesn't gravitate but drags on the bodies moving
+//! through it.
+
+use bevy::prelude::*;
+
+use crate::components::{
+    consts, Acceleration, Force, Mass, Position, Radius, Real, RealVec3, Time, Velocity,
+};
+
+use super::{
+    disk::{Star, AU},
+    integrator::Body,
+    simulation::{PhysicsSet, SimClock, SimulationStep},
+    stars::{orbit_center, OrbitCenter},
+    Constants, Planet,
+};
+
+pub struct GasDragPlugin;
+
+impl Plugin for GasDragPlugin {
+    fn build(&self, app: &mut App) {
+        app // <no autoformat>
+            .init_resource::<GasDisk>()
+            .register_type::<Migration>()
+            .add_systems(
+                SimulationStep,
+                (gas_drag_system, migration_system).in_set(PhysicsSet::Forces),
+            );
+    }
+}
+
+/// How H/r grows with distance. This is what the T ∝ r^-½ profile of
+/// [`Star::temperature_at`] gives.
+const FLARING_INDEX: Real = 0.25;
+
+/// A gas disk around whatever each body orbits, moving with it: a single
+/// star, or the barycenter of a close binary.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct GasDisk {
+    pub enabled: bool,
+    /// Surface density one AU out at the start of the simulation.
+    pub surface_density: Real,
+    /// The surface density falls off as Σ ∝ r^-p. 1.5 is the minimum-mass
+    /// solar nebula.
+    pub surface_density_index: Real,
+    /// Scale height over distance, H/r, one AU out.
+    pub aspect_ratio: Real,
+    /// The gas decays as exp(-t/τ) with this τ.
+    pub dissipation_time: Real,
+    /// Mean free path of a gas molecule in the midplane one AU out at the
+    /// start of the simulation. It grows as the gas thins out. Bodies smaller
+    /// than about this size feel Epstein drag, bigger ones Stokes drag.
+    pub mean_free_path: Real,
+    /// Whether planets feel the type I torques of the disk.
+    pub migration: bool,
+    /// Factor applied to every type I rate. The isothermal rates of Tanaka et
+    /// al. (2002) are known to be too fast, and this disk is far denser
+    /// relative to its planets than a real one, so they are scaled down.
+    pub migration_efficiency: Real,
+}
+
+impl Default for GasDisk {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            surface_density: 1e-3,
+            surface_density_index: 1.5,
+            aspect_ratio: 0.05,
+            dissipation_time: 3000.0,
+            mean_free_path: 1.0,
+            migration: false,
+            migration_efficiency: 0.01,
+        }
+    }
+}
+
+impl GasDisk {
+    /// Fraction of the gas which hasn't dissipated after `elapsed` time.
+    pub fn remaining(&self, elapsed: Time) -> Real {
+        (-elapsed.0 / self.dissipation_time).exp()
+    }
+
+    /// Surface density `r` from the star after `elapsed` time.
+    pub fn surface_density_at(&self, r: Real, elapsed: Time) -> Real {
+        self.surface_density * (r / AU).powf(-self.surface_density_index) * self.remaining(elapsed)
+    }
+
+    /// H/r at a distance `r` from the star.
+    pub fn aspect_ratio_at(&self, r: Real) -> Real {
+        self.aspect_ratio * (r / AU).powf(FLARING_INDEX)
+    }
+
+    /// Gas density `r` from the star and `z` above the midplane.
+    pub fn density_at(&self, r: Real, z: Real, elapsed: Time) -> Real {
+        let h = self.aspect_ratio_at(r) * r;
+        self.surface_density_at(r, elapsed) / ((2.0 * consts::PI).sqrt() * h)
+            * (-0.5 * (z / h).powi(2)).exp()
+    }
+
+    /// Fraction η by which the gas orbits slower than a body would, being
+    /// partly held up by its own pressure: v = (1 - η) v_K.
+    pub fn sub_keplerian_fraction(&self, r: Real) -> Real {
+        // The midplane pressure falls off as r^-n.
+        let n = self.surface_density_index + 2.0 - FLARING_INDEX;
+        0.5 * self.aspect_ratio_at(r).powi(2) * n
+    }
+
+    /// Time it takes drag to bring a body to rest relative to the gas, for a
+    /// sphere of the given radius and mass moving at `rel_speed` through gas
+    /// of `density` with thermal speed `thermal_speed`.
+    fn stopping_time(
+        &self,
+        radius: Radius,
+        mass: Mass,
+        rel_speed: Real,
+        density: Real,
+        thermal_speed: Real,
+    ) -> Real {
+        let s = radius.0;
+        let solid_density = mass.0 / (4.0 / 3.0 * consts::PI * s.powi(3));
+
+        let midplane_density_1_au = self.density_at(AU, 0.0, Time::ZERO);
+        let mean_free_path = self.mean_free_path * midplane_density_1_au / density;
+
+        let epstein = solid_density * s / (density * thermal_speed);
+        if s < 2.25 * mean_free_path {
+            return epstein;
+        }
+
+        // Stokes, with the drag coefficient's dependence on the Reynolds number
+        // from Weidenschilling (1977).
+        let viscosity = 0.5 * mean_free_path * thermal_speed;
+        let reynolds = 2.0 * s * rel_speed / viscosity;
+        if reynolds < 1.0 {
+            // C_D = 24/Re, which makes the stopping time independent of speed.
+            return 2.0 * solid_density * s * s / (9.0 * viscosity * density);
+        }
+
+        let drag_coefficient = if reynolds < 800.0 {
+            24.0 * reynolds.powf(-0.6)
+        } else {
+            0.44
+        };
+
+        8.0 * solid_density * s / (3.0 * drag_coefficient * density * rel_speed)
+    }
+}
+
+type GasDragPlanetsData<'a, 'b, 'c, 'd, 'e> = (
+    &'a Position,
+    &'b Velocity,
+    &'c Mass,
+    &'d Radius,
+    &'e mut Force,
+);
+
+/// Acceleration of `body` toward the velocity of the gas around `center`.
+fn drag_acceleration(
+    gas: &GasDisk,
+    grav_const: Real,
+    center: &OrbitCenter,
+    body: &Body,
+    clock: &SimClock,
+) -> Acceleration {
+    let offset = body.pos.0 - center.pos;
+    let z = offset.y;
+    let r = RealVec3::new(offset.x, 0.0, offset.z).length();
+    if r == 0.0 {
+        return Acceleration::ZERO;
+    }
+
+    let density = gas.density_at(r, z, clock.elapsed);
+    if density <= 0.0 {
+        return Acceleration::ZERO;
+    }
+
+    let kepler_speed = (grav_const * center.mass.0 / r).sqrt();
+    let gas_speed = (1.0 - gas.sub_keplerian_fraction(r)) * kepler_speed;
+    let gas_vel =
+        center.vel + Velocity(gas_speed * RealVec3::Y.cross(offset.normalize()).normalize());
+
+    let rel_vel = (body.vel - gas_vel).0;
+    let sound_speed = gas.aspect_ratio_at(r) * kepler_speed;
+    let thermal_speed = (8.0 / consts::PI).sqrt() * sound_speed;
+
+    let stopping_time = gas.stopping_time(
+        body.radius,
+        body.mass,
+        rel_vel.length(),
+        density,
+        thermal_speed,
+    );
+
+    // Drag is held fixed over the step, so a body that would stop in less
+    // than a step would overshoot. Let it match the gas at most.
+    let stopping_time = stopping_time.max(clock.dt.0);
+
+    Acceleration(-rel_vel / stopping_time)
+}
+
+/// Pulls every body toward the velocity of the gas around it.
+fn gas_drag_system(
+    mut planets: Query<GasDragPlanetsData, (With<Planet>, Without<Star>)>,
+    stars: Query<(&Position, &Velocity, &Mass), With<Star>>,
+    gas: Res<GasDisk>,
+    constants: Res<Constants>,
+    clock: Res<SimClock>,
+) {
+    if !gas.enabled {
+        return;
+    }
+
+    for (&pos, &vel, &mass, &radius, mut force) in &mut planets {
+        let Some(center) = orbit_center(&stars, pos.0) else {
+            continue;
+        };
+
+        let body = Body {
+            pos,
+            vel,
+            mass,
+            radius,
+        };
+        *force += mass * drag_acceleration(&gas, constants.grav_const, &center, &body, &clock);
+    }
+}
+
+/// The type I migration force on a planet, as of the last substep.
+#[derive(Component, Default, Reflect, Debug, Clone, Copy)]
+pub struct Migration {
+    pub force: Force,
+}
+
+/// Timescales of the type I torques on a body of mass `mass` in a circular
+/// orbit `r` from a star of mass `star_mass`: migration t_m, eccentricity
+/// damping t_e and inclination damping t_i. From Tanaka et al. (2002) and
+/// Tanaka & Ward (2004), in the form used by Cresswell & Nelson (2008).
+fn migration_timescales(
+    gas: &GasDisk,
+    grav_const: Real,
+    star_mass: Mass,
+    mass: Mass,
+    r: Real,
+    elapsed: Time,
+) -> (Real, Real, Real) {
+    let h = gas.aspect_ratio_at(r);
+    let sigma = gas.surface_density_at(r, elapsed);
+    let omega = (grav_const * star_mass.0 / r.powi(3)).sqrt();
+
+    let t_wave = (star_mass.0 / mass.0) * (star_mass.0 / (sigma * r * r)) * h.powi(4)
+        / omega
+        / gas.migration_efficiency;
+
+    let t_m = 2.0 * t_wave / (h * h * (2.7 + 1.1 * gas.surface_density_index));
+    let t_e = t_wave / 0.780;
+    let t_i = t_wave / 0.544;
+
+    (t_m, t_e, t_i)
+}
+
+type MigrationPlanetsData<'a, 'b, 'c, 'd, 'e, 'f> = (
+    &'a Position,
+    &'b Velocity,
+    &'c Mass,
+    &'d Radius,
+    &'e mut Force,
+    &'f mut Migration,
+);
+
+/// Acceleration of `body` from the type I torques of the disk around
+/// `center`: a steady drift inward, and damping of eccentricity and
+/// inclination. Zero if migration is off or there's no gas.
+fn migration_acceleration(
+    gas: &GasDisk,
+    grav_const: Real,
+    center: &OrbitCenter,
+    body: &Body,
+    clock: &SimClock,
+) -> Acceleration {
+    if !gas.enabled || !gas.migration {
+        return Acceleration::ZERO;
+    }
+
+    let offset = body.pos.0 - center.pos;
+    let r = offset.length();
+    if r == 0.0 || gas.surface_density_at(r, clock.elapsed) <= 0.0 {
+        return Acceleration::ZERO;
+    }
+
+    let rel_vel = (body.vel - center.vel).0;
+    let (t_m, t_e, t_i) =
+        migration_timescales(gas, grav_const, center.mass, body.mass, r, clock.elapsed);
+
+    // Like drag, these are held fixed over the step and mustn't overshoot.
+    let dt = clock.dt.0;
+    Acceleration(
+        -rel_vel / t_m.max(dt)
+            - 2.0 * rel_vel.dot(offset) * offset / (r * r * t_e.max(dt))
+            - RealVec3::new(0.0, rel_vel.y, 0.0) / t_i.max(dt),
+    )
+}
+
+/// Applies the disk's type I torques to every planet.
+fn migration_system(
+    mut planets: Query<MigrationPlanetsData, (With<Planet>, Without<Star>)>,
+    stars: Query<(&Position, &Velocity, &Mass), With<Star>>,
+    gas: Res<GasDisk>,
+    constants: Res<Constants>,
+    clock: Res<SimClock>,
+) {
+    for (&pos, &vel, &mass, &radius, mut force, mut migration) in &mut planets {
+        migration.force = Force::ZERO;
+
+        let Some(center) = orbit_center(&stars, pos.0) else {
+            continue;
+        };
+
+        let body = Body {
+            pos,
+            vel,
+            mass,
+            radius,
+        };
+        migration.force =
+            mass * migration_acceleration(&gas, constants.grav_const, &center, &body, &clock);
+        *force += migration.force;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn epstein_and_stokes_drag_meet() {
+        let gas = GasDisk::default();
+        let density = gas.density_at(AU, 0.0, Time::ZERO);
+        let solid_density = 0.01;
+
+        let stopping_time = |s: Real| {
+            let mass = Mass(solid_density * 4.0 / 3.0 * consts::PI * s.powi(3));
+            gas.stopping_time(Radius(s), mass, 1e-6, density, 1.0)
+        };
+
+        let boundary = 2.25 * gas.mean_free_path;
+        let epstein = stopping_time(0.999 * boundary);
+        let stokes = stopping_time(1.001 * boundary);
+
+        assert!((stokes / epstein - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn drag_damps_eccentricity_and_drifts_inward() {
+        let gas = GasDisk::default();
+        let grav_const = Constants::default().grav_const;
+        let star = OrbitCenter {
+            pos: RealVec3::ZERO,
+            vel: Velocity::ZERO,
+            mass: Mass(1000.0),
+        };
+        let mu = grav_const * star.mass.0;
+
+        // A pebble whose stopping time is about 1/Ω, which drifts fastest,
+        // started on an orbit with e ≈ 0.2.
+        let kepler_speed = (mu / AU).sqrt();
+        let mut body = Body {
+            pos: Position(RealVec3::new(AU, 0.0, 0.0)),
+            vel: Velocity(RealVec3::new(0.0, 0.0, -1.1 * kepler_speed)),
+            mass: Mass(3e-5),
+            radius: Radius(0.1),
+        };
+        let mut clock = SimClock {
+            dt: Time(0.01),
+            ..default()
+        };
+
+        // Semi-major axis and eccentricity from the orbit's energy and angular
+        // momentum.
+        let orbit = |body: &Body| {
+            let r = body.pos.0.length();
+            let energy = 0.5 * body.vel.0.length_squared() - mu / r;
+            let a = -mu / (2.0 * energy);
+            let h = body.pos.0.cross(body.vel.0).length();
+            (a, (1.0 - h * h / (mu * a)).max(0.0).sqrt())
+        };
+        let (a0, e0) = orbit(&body);
+
+        for _ in 0..20_000 {
+            let gravity = -mu * body.pos.0 / body.pos.0.length().powi(3);
+            let drag = drag_acceleration(&gas, grav_const, &star, &body, &clock);
+            body.vel += (Acceleration(gravity) + drag) * clock.dt;
+            body.pos.0 += body.vel * clock.dt;
+            clock.elapsed += clock.dt;
+        }
+
+        let (a, e) = orbit(&body);
+        assert!(a < 0.95 * a0);
+        assert!(e < 0.1 * e0);
+    }
+
+    #[test]
+    fn migration_timescales_scale_with_surface_density_and_aspect_ratio() {
+        let gas = GasDisk::default();
+        let timescales = |gas: &GasDisk| {
+            let (t_m, t_e, t_i) =
+                migration_timescales(gas, 20.0, Mass(1000.0), Mass(10.0), AU, Time::ZERO);
+            [t_m, t_e, t_i]
+        };
+        let base = timescales(&gas);
+
+        let denser = timescales(&GasDisk {
+            surface_density: 2.0 * gas.surface_density,
+            ..gas
+        });
+        let thicker = timescales(&GasDisk {
+            aspect_ratio: 2.0 * gas.aspect_ratio,
+            ..gas
+        });
+
+        // Every rate goes as Σ. The wave timescale goes as h⁴, and t_m is a
+        // further 1/h² longer than it (Tanaka et al. 2002).
+        for (t, t_dense) in base.iter().zip(denser) {
+            assert!((t_dense / t - 0.5).abs() < 1e-4);
+        }
+        for (t, t_thick, power) in [
+            (base[0], thicker[0], 2),
+            (base[1], thicker[1], 4),
+            (base[2], thicker[2], 4),
+        ] {
+            assert!((t_thick / t / (2.0 as Real).powi(power) - 1.0).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn no_migration_force_when_disabled() {
+        let star = OrbitCenter {
+            pos: RealVec3::ZERO,
+            vel: Velocity::ZERO,
+            mass: Mass(1000.0),
+        };
+        let body = Body {
+            pos: Position(RealVec3::new(AU, 1.0, 0.0)),
+            vel: Velocity(RealVec3::new(0.5, 0.2, -14.0)),
+            mass: Mass(10.0),
+            radius: Radius(1.0),
+        };
+        let clock = SimClock {
+            dt: Time(0.01),
+            ..default()
+        };
+        let acceleration = |migration| {
+            let gas = GasDisk {
+                migration,
+                ..default()
+            };
+            migration_acceleration(&gas, 20.0, &star, &body, &clock)
+        };
+
+        assert_eq!(acceleration(false).0, RealVec3::ZERO);
+
+        // When on, it slows the planet down and pulls it toward the midplane.
+        let on = acceleration(true).0;
+        assert!(on.dot(body.vel.0) < 0.0);
+        assert!(on.y < 0.0);
+    }
+}
@@ -0,0 +1,326 @@
+//! Initial conditions for a disk of bodies, drawn from the distributions
+//! planet formation models usually start from.
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::components::{consts, Mass, Position, Real, Velocity};
+
+use super::{
+    disk::Star,
+    orbit::OrbitalElements,
+    simulation::SimRng,
+    stars::{orbit_center, StarSystemPreset},
+    Constants, Planet, SpawnPlanetEvent,
+};
+
+pub struct DiskGeneratorPlugin {
+    pub generator: DiskGenerator,
+}
+
+impl Plugin for DiskGeneratorPlugin {
+    fn build(&self, app: &mut App) {
+        app // <no autoformat>
+            .insert_resource(self.generator)
+            .add_event::<GenerateDiskEvent>()
+            .add_systems(Startup, generate_initial_disk)
+            .add_systems(
+                Update,
+                (fit_radii_to_stars_system, generate_disk_system).chain(),
+            );
+    }
+}
+
+/// How body masses are drawn.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MassFunction {
+    /// dN/dM ∝ M^-α between the minimum and maximum mass.
+    #[default]
+    PowerLaw,
+    /// ln M is normally distributed, clipped to the minimum and maximum mass.
+    LogNormal,
+}
+
+impl MassFunction {
+    pub const ALL: [Self; 2] = [Self::PowerLaw, Self::LogNormal];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::PowerLaw => "Power Law",
+            Self::LogNormal => "Log-normal",
+        }
+    }
+}
+
+/// Settings for generating a disk of bodies on near-circular, near-coplanar
+/// orbits about the stars.
+///
+/// The defaults lay bodies out the way the simulation always has: spread
+/// evenly over [`StarSystemPreset::planet_orbits`], with masses drawn evenly
+/// from 2 to 52. Whenever a different star system is spawned, the radii are
+/// moved to its [`StarSystemPreset::planet_orbits`].
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct DiskGenerator {
+    pub count: usize,
+    /// Range of semi-major axes, measured from what the bodies orbit.
+    pub inner_radius: Real,
+    pub outer_radius: Real,
+    /// The bodies' surface density falls off as Σ ∝ r^-p.
+    pub surface_density_index: Real,
+    pub mass_function: MassFunction,
+    pub min_mass: Real,
+    pub max_mass: Real,
+    /// α of [`MassFunction::PowerLaw`].
+    pub mass_index: Real,
+    /// Median mass of [`MassFunction::LogNormal`].
+    pub median_mass: Real,
+    /// Standard deviation of ln M for [`MassFunction::LogNormal`].
+    pub mass_sigma: Real,
+    /// Scale σ of the Rayleigh distribution eccentricities are drawn from.
+    pub eccentricity_scale: Real,
+    /// Scale σ, in radians, of the Rayleigh distribution inclinations are
+    /// drawn from.
+    pub inclination_scale: Real,
+    /// Least separation between any two bodies' semi-major axes, in mutual
+    /// Hill radii. The simulation's planets are heavy enough for their Hill
+    /// spheres to be a good fraction of their orbits, so a spacing much past
+    /// one leaves room for only a handful of bodies.
+    pub min_spacing: Real,
+}
+
+impl Default for DiskGenerator {
+    fn default() -> Self {
+        let orbits = StarSystemPreset::default().planet_orbits();
+        Self {
+            count: 25,
+            inner_radius: orbits.start,
+            outer_radius: orbits.end,
+            surface_density_index: 1.0,
+            mass_function: MassFunction::default(),
+            min_mass: 2.0,
+            max_mass: 52.0,
+            mass_index: 0.0,
+            median_mass: 10.0,
+            mass_sigma: 1.0,
+            eccentricity_scale: 0.01,
+            inclination_scale: 0.005,
+            min_spacing: 0.0,
+        }
+    }
+}
+
+/// Eccentricities drawn past this are clipped to it, so every orbit stays
+/// well bound.
+const MAX_ECCENTRICITY: Real = 0.9;
+
+/// Draws to make for each body before giving up on fitting it in.
+const ATTEMPTS_PER_BODY: usize = 100;
+
+impl DiskGenerator {
+    /// Spreads the bodies over where planets can orbit in `preset`.
+    pub fn fit_radii_to(&mut self, preset: StarSystemPreset) {
+        let orbits = preset.planet_orbits();
+        self.inner_radius = orbits.start;
+        self.outer_radius = orbits.end;
+    }
+
+    /// Draws a semi-major axis. Σ ∝ r^-p puts dN/dr ∝ r^(1-p).
+    fn semi_major_axis(&self, rng: &mut impl Rng) -> Real {
+        sample_power_law(
+            rng,
+            self.inner_radius,
+            self.outer_radius,
+            1.0 - self.surface_density_index,
+        )
+    }
+
+    fn mass(&self, rng: &mut impl Rng) -> Mass {
+        let mass = match self.mass_function {
+            MassFunction::PowerLaw => {
+                sample_power_law(rng, self.min_mass, self.max_mass, -self.mass_index)
+            }
+            MassFunction::LogNormal => {
+                (self.median_mass.ln() + self.mass_sigma * sample_normal(rng)).exp()
+            }
+        };
+        Mass(mass.clamp(self.min_mass, self.max_mass))
+    }
+
+    /// Positions and velocities of the bodies, about whatever each orbits of
+    /// `stars`. Bodies that can't be fitted in [`Self::min_spacing`] from the
+    /// rest are left out, so there may be fewer than [`Self::count`].
+    pub fn generate<'a>(
+        &self,
+        rng: &mut impl Rng,
+        grav_const: Real,
+        stars: impl IntoIterator<Item = (&'a Position, &'a Velocity, &'a Mass)> + Clone,
+    ) -> Vec<SpawnPlanetEvent> {
+        let mut placed: Vec<(Real, Mass)> = vec![];
+        let mut events = vec![];
+
+        for _ in 0..self.count {
+            let fit = (0..ATTEMPTS_PER_BODY).find_map(|_| {
+                let a = self.semi_major_axis(rng);
+                let mass = self.mass(rng);
+
+                let elements = OrbitalElements::from_mean_anomaly(
+                    a,
+                    sample_rayleigh(rng, self.eccentricity_scale).min(MAX_ECCENTRICITY),
+                    sample_rayleigh(rng, self.inclination_scale),
+                    rng.gen_range(0.0..consts::TAU),
+                    rng.gen_range(0.0..consts::TAU),
+                    rng.gen_range(0.0..consts::TAU),
+                );
+
+                // The stars sit near the origin, so that's good enough to tell
+                // which of them the body goes around.
+                let center =
+                    orbit_center(stars.clone(), elements.position_at(elements.true_anomaly))?;
+
+                let spaced = placed.iter().all(|&(other_a, other_mass)| {
+                    let hill = ((mass + other_mass).0 / (3.0 * center.mass.0)).cbrt()
+                        * 0.5
+                        * (a + other_a);
+                    (a - other_a).abs() >= self.min_spacing * hill
+                });
+
+                spaced.then_some((a, mass, elements, center))
+            });
+
+            let Some((a, mass, elements, center)) = fit else {
+                continue;
+            };
+            placed.push((a, mass));
+
+            let (pos, vel) = elements.to_state(grav_const * (center.mass + mass).0);
+            events.push(SpawnPlanetEvent {
+                pos: Some(center.pos + pos),
+                vel: Some(center.vel + Velocity(vel)),
+                mass: Some(mass),
+                ..default()
+            });
+        }
+
+        events
+    }
+}
+
+/// Draws from dN/dx ∝ x^`exponent` between `min` and `max` by inverting its
+/// cumulative distribution.
+fn sample_power_law(rng: &mut impl Rng, min: Real, max: Real, exponent: Real) -> Real {
+    let u = rng.gen_range(0.0..1.0);
+    let k = exponent + 1.0;
+    if k.abs() < 1e-6 {
+        min * (max / min).powf(u)
+    } else {
+        (min.powf(k) + u * (max.powf(k) - min.powf(k))).powf(1.0 / k)
+    }
+}
+
+/// Draws from the standard normal distribution with the Box–Muller transform.
+fn sample_normal(rng: &mut impl Rng) -> Real {
+    let u: Real = 1.0 - rng.gen_range(0.0..1.0);
+    let v: Real = rng.gen_range(0.0..1.0);
+    (-2.0 * u.ln()).sqrt() * (consts::TAU * v).cos()
+}
+
+/// Draws from the Rayleigh distribution with scale `sigma`.
+fn sample_rayleigh(rng: &mut impl Rng, sigma: Real) -> Real {
+    let u: Real = 1.0 - rng.gen_range(0.0..1.0);
+    sigma * (-2.0 * u.ln()).sqrt()
+}
+
+/// Asks for a disk to be generated from the [`DiskGenerator`] resource.
+#[derive(Event, Clone, Copy)]
+pub struct GenerateDiskEvent {
+    /// Whether every planet but the stars is removed first.
+    pub replace: bool,
+}
+
+fn generate_initial_disk(mut ewriter: EventWriter<GenerateDiskEvent>) {
+    ewriter.send(GenerateDiskEvent { replace: false });
+}
+
+/// Keeps the generator's radii clear of the stars when the star system is
+/// swapped out. The star system the simulation starts with is left to
+/// whatever the generator was configured with.
+fn fit_radii_to_stars_system(preset: Res<StarSystemPreset>, mut generator: ResMut<DiskGenerator>) {
+    if preset.is_changed() && !preset.is_added() {
+        generator.fit_radii_to(*preset);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn generate_disk_system(
+    mut ereader: EventReader<GenerateDiskEvent>,
+    mut commands: Commands,
+    mut spawn_events: EventWriter<SpawnPlanetEvent>,
+    generator: Res<DiskGenerator>,
+    constants: Res<Constants>,
+    planets: Query<Entity, (With<Planet>, Without<Star>)>,
+    stars: Query<(&Position, &Velocity, &Mass), With<Star>>,
+    mut rng: ResMut<SimRng>,
+) {
+    for event in ereader.iter() {
+        if event.replace {
+            for entity in &planets {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+
+        spawn_events.send_batch(generator.generate(&mut rng.0, constants.grav_const, &stars));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::components::RealVec3;
+
+    #[test]
+    fn bodies_are_spaced_and_in_range() {
+        let generator = DiskGenerator {
+            count: 40,
+            min_spacing: 0.3,
+            eccentricity_scale: 0.0,
+            inclination_scale: 0.0,
+            ..default()
+        };
+        let sun = (Position(RealVec3::ZERO), Velocity::ZERO, Mass(1000.0));
+        let stars = [(&sun.0, &sun.1, &sun.2)];
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let events = generator.generate(&mut rng, 20.0, stars);
+        assert!(!events.is_empty());
+
+        let bodies = events
+            .iter()
+            .map(|event| (event.pos.unwrap().length(), event.mass.unwrap()))
+            .collect::<Vec<_>>();
+
+        for (i, &(a, mass)) in bodies.iter().enumerate() {
+            assert!(a >= 0.999 * generator.inner_radius && a <= 1.001 * generator.outer_radius);
+            assert!(mass.0 >= generator.min_mass && mass.0 <= generator.max_mass);
+
+            for &(other_a, other_mass) in &bodies[..i] {
+                let hill = ((mass + other_mass).0 / 3000.0).cbrt() * 0.5 * (a + other_a);
+                assert!((a - other_a).abs() >= 0.99 * generator.min_spacing * hill);
+            }
+        }
+    }
+
+    #[test]
+    fn default_radii_follow_the_star_system() {
+        let mut generator = DiskGenerator::default();
+        let orbits = StarSystemPreset::default().planet_orbits();
+        assert_eq!(generator.inner_radius..generator.outer_radius, orbits);
+
+        generator.fit_radii_to(StarSystemPreset::PType);
+        assert_eq!(
+            generator.inner_radius..generator.outer_radius,
+            StarSystemPreset::PType.planet_orbits()
+        );
+    }
+}
@@ -0,0 +1,323 @@
+use std::num::NonZeroUsize;
+
+use bevy::tasks::ComputeTaskPool;
+
+use crate::components::{Energy, Force, Mass, Real, RealVec3};
+
+use super::{integrator::Body, octree::Octree, Constants};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GravitySolver {
+    /// Exact O(n²) sum over every pair of bodies.
+    Pairwise,
+    /// The exact sum, spread over the [`ComputeTaskPool`]. See
+    /// [`parallel_pairwise_forces`] for how closely it matches [`Self::Pairwise`].
+    ParallelPairwise,
+    /// O(n log n) Barnes–Hut approximation. See [`Constants::opening_angle`].
+    BarnesHut,
+}
+
+impl Default for GravitySolver {
+    /// [`Self::ParallelPairwise`] on multi-core machines, [`Self::Pairwise`]
+    /// otherwise.
+    fn default() -> Self {
+        let cores = std::thread::available_parallelism().map_or(1, NonZeroUsize::get);
+        if cores > 1 {
+            Self::ParallelPairwise
+        } else {
+            Self::Pairwise
+        }
+    }
+}
+
+impl GravitySolver {
+    pub const ALL: [Self; 3] = [Self::Pairwise, Self::ParallelPairwise, Self::BarnesHut];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Pairwise => "Pairwise (exact)",
+            Self::ParallelPairwise => "Pairwise (parallel)",
+            Self::BarnesHut => "Barnes-Hut",
+        }
+    }
+}
+
+/// Computes the gravitational force on every body with the solver selected in
+/// `constants`. Returns the octree if one was built.
+pub fn forces(bodies: &[Body], constants: &Constants, forces: &mut [Force]) -> Option<Octree> {
+    match constants.gravity_solver {
+        GravitySolver::Pairwise => {
+            pairwise_forces(bodies, constants, forces);
+            None
+        }
+        GravitySolver::ParallelPairwise => {
+            parallel_pairwise_forces(bodies, constants, forces);
+            None
+        }
+        GravitySolver::BarnesHut => {
+            let tree = Octree::build(bodies);
+            tree.forces(bodies, constants, forces);
+            Some(tree)
+        }
+    }
+}
+
+/// Exact O(n²) gravitational forces between every pair of bodies.
+pub fn pairwise_forces(bodies: &[Body], constants: &Constants, forces: &mut [Force]) {
+    forces.fill(Force::ZERO);
+
+    for i in 0..bodies.len() {
+        for j in (i + 1)..bodies.len() {
+            if let Some(force) = pair_force(&bodies[i], &bodies[j], constants) {
+                forces[i] += Force(force);
+                forces[j] -= Force(force);
+            }
+        }
+    }
+}
+
+/// Smallest number of bodies worth handing to a separate task.
+const MIN_BODIES_PER_TASK: usize = 32;
+
+/// Exact gravitational forces, computed in parallel on the [`ComputeTaskPool`].
+///
+/// Each task owns a disjoint chunk of `forces` and sums every other body's pull
+/// on its bodies in index order, so the result doesn't depend on how many
+/// threads there are or how they're scheduled.
+///
+/// The terms are added in the same order as in [`pairwise_forces`], but each
+/// pair is evaluated once per body rather than once per pair, so the mass
+/// product can round differently. Each body's net force therefore agrees with
+/// the serial path to within about `1e-6` times the sum of the magnitudes of
+/// the individual pair forces acting on it.
+pub fn parallel_pairwise_forces(bodies: &[Body], constants: &Constants, forces: &mut [Force]) {
+    let pool = ComputeTaskPool::get();
+    let chunk_size = bodies
+        .len()
+        .div_ceil(pool.thread_num().max(1))
+        .max(MIN_BODIES_PER_TASK);
+
+    pool.scope(|scope| {
+        for (chunk_idx, chunk) in forces.chunks_mut(chunk_size).enumerate() {
+            scope.spawn(async move {
+                let offset = chunk_idx * chunk_size;
+                for (k, force) in chunk.iter_mut().enumerate() {
+                    let i = offset + k;
+                    *force = bodies
+                        .iter()
+                        .enumerate()
+                        .filter(|&(j, _)| j != i)
+                        .filter_map(|(_, other)| pair_force(&bodies[i], other, constants))
+                        .map(Force)
+                        .sum();
+                }
+            });
+        }
+    });
+}
+
+/// The force `parent` exerts on `sat`.
+///
+/// Overlapping bodies are about to be merged, so they don't attract and this
+/// returns `None`.
+pub fn pair_force(sat: &Body, parent: &Body, constants: &Constants) -> Option<RealVec3> {
+    let sat_to_parent = parent.pos.0 - sat.pos.0;
+    let radii_sum = sat.radius + parent.radius;
+
+    if sat_to_parent.length_squared() < radii_sum.0 * radii_sum.0 {
+        return None;
+    }
+
+    Some(attraction(
+        constants,
+        sat.mass.0,
+        parent.mass.0,
+        sat_to_parent,
+    ))
+}
+
+/// How the pull between two bodies is tamed at small separations, where the
+/// Newtonian 1/r² force would blow up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Softening {
+    /// Newtonian gravity with the separation clamped to
+    /// [`Constants::min_attraction_dist`]. The force jumps where the clamp
+    /// kicks in.
+    #[default]
+    None,
+    /// Plummer softening with length [`Constants::plummer_epsilon`]. Smooth
+    /// everywhere, but never exactly Newtonian.
+    Plummer,
+    /// The cubic spline kernel used by SPH and tree codes (Monaghan & Lattanzio
+    /// 1985; Springel 2005). Exactly Newtonian beyond
+    /// [`Constants::spline_length`].
+    CubicSpline,
+}
+
+impl Softening {
+    pub const ALL: [Self; 3] = [Self::None, Self::Plummer, Self::CubicSpline];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::Plummer => "Plummer",
+            Self::CubicSpline => "Cubic Spline",
+        }
+    }
+}
+
+/// Magnitude of the softened attraction between two unit masses a distance
+/// `r` apart, with G = 1. Equal to 1/r² wherever the kernel isn't active.
+pub fn softened_force(constants: &Constants, r: Real) -> Real {
+    match constants.softening {
+        Softening::None => {
+            let min_dist = constants.min_attraction_dist;
+            1.0 / (r * r).max(min_dist * min_dist)
+        }
+        Softening::Plummer => {
+            let eps = constants.plummer_epsilon;
+            r / (r * r + eps * eps).powf(1.5)
+        }
+        Softening::CubicSpline => {
+            let h = constants.spline_length;
+            if r >= h {
+                return 1.0 / (r * r);
+            }
+            let u = r / h;
+            let k = if u < 0.5 {
+                32.0 / 3.0 + u * u * (32.0 * u - 38.4)
+            } else {
+                64.0 / 3.0 - 48.0 * u + 38.4 * u * u
+                    - 32.0 / 3.0 * u * u * u
+                    - 1.0 / (15.0 * u * u * u)
+            };
+            r / (h * h * h) * k
+        }
+    }
+}
+
+/// Softened potential energy of two unit masses a distance `r` apart, with
+/// G = 1. Its derivative is [`softened_force`], and it is -1/r wherever the
+/// kernel isn't active.
+pub fn softened_potential(constants: &Constants, r: Real) -> Real {
+    match constants.softening {
+        Softening::None => {
+            // Below `min_attraction_dist` the force stops growing, so the
+            // potential continues linearly from its value there.
+            let min_dist = constants.min_attraction_dist;
+            if r >= min_dist {
+                -1.0 / r
+            } else {
+                -(2.0 * min_dist - r) / (min_dist * min_dist)
+            }
+        }
+        Softening::Plummer => {
+            let eps = constants.plummer_epsilon;
+            -1.0 / (r * r + eps * eps).sqrt()
+        }
+        Softening::CubicSpline => {
+            let h = constants.spline_length;
+            if r >= h {
+                return -1.0 / r;
+            }
+            let u = r / h;
+            let w = if u < 0.5 {
+                -2.8 + u * u * (16.0 / 3.0 + u * u * (6.4 * u - 9.6))
+            } else {
+                -3.2 + 1.0 / (15.0 * u)
+                    + u * u * (32.0 / 3.0 + u * (-16.0 + u * (9.6 - 32.0 / 15.0 * u)))
+            };
+            w / h
+        }
+    }
+}
+
+/// Attraction of a point mass `sat_mass` toward a point mass `parent_mass`
+/// separated by `sat_to_parent`, softened according to
+/// [`Constants::softening`].
+pub fn attraction(
+    constants: &Constants,
+    sat_mass: Real,
+    parent_mass: Real,
+    sat_to_parent: RealVec3,
+) -> RealVec3 {
+    let toward_parent = sat_to_parent.normalize_or_zero();
+    let r = sat_to_parent.length();
+
+    constants.grav_const * sat_mass * parent_mass * softened_force(constants, r) * toward_parent
+}
+
+/// Potential energy of two point masses a distance `dist` apart, consistent
+/// with [`attraction`].
+pub fn potential(constants: &Constants, m1: Real, m2: Real, dist: Real) -> Energy {
+    Energy(constants.grav_const * m1 * m2 * softened_potential(constants, dist))
+}
+
+/// Total potential energy of every pair of bodies.
+pub fn total_potential(constants: &Constants, bodies: &[(RealVec3, Mass)]) -> Energy {
+    let mut total = Energy::ZERO;
+    for (i, &(p1, m1)) in bodies.iter().enumerate() {
+        for &(p2, m2) in &bodies[i + 1..] {
+            total += potential(constants, m1.0, m2.0, p1.distance(p2));
+        }
+    }
+    total
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::tasks::TaskPool;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+    use crate::components::{Mass, Position, Radius, Velocity};
+
+    fn random_bodies(count: usize) -> Vec<Body> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut coord = || rng.gen_range(-100.0..100.0);
+        (0..count)
+            .map(|_| Body {
+                pos: Position(RealVec3::new(coord(), coord(), coord())),
+                vel: Velocity::ZERO,
+                mass: Mass(coord().abs() + 1.0),
+                radius: Radius(0.01),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parallel_pairwise_matches_serial_and_is_deterministic() {
+        ComputeTaskPool::init(TaskPool::default);
+
+        let bodies = random_bodies(200);
+        let constants = Constants::default();
+        let solve = |solver| {
+            let constants = Constants {
+                gravity_solver: solver,
+                ..Constants::default()
+            };
+            let mut out = vec![Force::ZERO; bodies.len()];
+            forces(&bodies, &constants, &mut out);
+            out
+        };
+
+        let serial = solve(GravitySolver::Pairwise);
+        let parallel = solve(GravitySolver::ParallelPairwise);
+
+        for (i, (s, p)) in serial.iter().zip(&parallel).enumerate() {
+            let magnitude_sum: Real = bodies
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i)
+                .filter_map(|(_, other)| pair_force(&bodies[i], other, &constants))
+                .map(RealVec3::length)
+                .sum();
+            assert!((s.0 - p.0).length() <= 1e-6 * magnitude_sum);
+        }
+
+        let again = solve(GravitySolver::ParallelPairwise);
+        for (p, q) in parallel.iter().zip(&again) {
+            assert_eq!(p.0.to_array(), q.0.to_array());
+        }
+    }
+}
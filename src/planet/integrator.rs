@@ -0,0 +1,222 @@
+use crate::components::{Acceleration, Force, Mass, Position, Radius, Real, Time, Velocity};
+
+/// The state of a single body as seen by an [`Integrator`].
+#[derive(Clone, Copy)]
+pub struct Body {
+    pub pos: Position,
+    pub vel: Velocity,
+    pub mass: Mass,
+    pub radius: Radius,
+}
+
+/// Computes the net force on every body in the given state. `forces` has the
+/// same length as the bodies slice and must be completely overwritten.
+pub type ForceFn<'a> = dyn FnMut(&[Body], &mut [Force]) + 'a;
+
+pub trait Integrator: Send + Sync {
+    /// Advances `bodies` by `dt`, calling `forces` as many times as the method
+    /// needs.
+    fn step(&self, bodies: &mut [Body], dt: Time, forces: &mut ForceFn);
+
+    /// Order of accuracy. The local error of one step scales like
+    /// `dt^(order + 1)`.
+    fn order(&self) -> u32;
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntegratorKind {
+    #[default]
+    SemiImplicitEuler,
+    Leapfrog,
+    VelocityVerlet,
+    Rk4,
+    Yoshida4,
+}
+
+impl IntegratorKind {
+    pub const ALL: [Self; 5] = [
+        Self::SemiImplicitEuler,
+        Self::Leapfrog,
+        Self::VelocityVerlet,
+        Self::Rk4,
+        Self::Yoshida4,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::SemiImplicitEuler => "Semi-implicit Euler",
+            Self::Leapfrog => "Leapfrog (KDK)",
+            Self::VelocityVerlet => "Velocity Verlet",
+            Self::Rk4 => "Runge-Kutta 4",
+            Self::Yoshida4 => "Yoshida 4",
+        }
+    }
+
+    pub fn integrator(self) -> &'static dyn Integrator {
+        match self {
+            Self::SemiImplicitEuler => &SemiImplicitEuler,
+            Self::Leapfrog => &Leapfrog,
+            Self::VelocityVerlet => &VelocityVerlet,
+            Self::Rk4 => &Rk4,
+            Self::Yoshida4 => &Yoshida4,
+        }
+    }
+}
+
+/// Updates velocity first, then moves using the new velocity. First order, but
+/// symplectic.
+pub struct SemiImplicitEuler;
+
+impl Integrator for SemiImplicitEuler {
+    fn step(&self, bodies: &mut [Body], dt: Time, forces: &mut ForceFn) {
+        let mut f = vec![Force::ZERO; bodies.len()];
+        forces(bodies, &mut f);
+        kick(bodies, &f, dt);
+        drift(bodies, dt);
+    }
+
+    fn order(&self) -> u32 {
+        1
+    }
+}
+
+/// Kick-drift-kick leapfrog. Second order and symplectic.
+pub struct Leapfrog;
+
+impl Integrator for Leapfrog {
+    fn step(&self, bodies: &mut [Body], dt: Time, forces: &mut ForceFn) {
+        let half_dt = Time(0.5 * dt.0);
+        let mut f = vec![Force::ZERO; bodies.len()];
+
+        forces(bodies, &mut f);
+        kick(bodies, &f, half_dt);
+        drift(bodies, dt);
+        forces(bodies, &mut f);
+        kick(bodies, &f, half_dt);
+    }
+
+    fn order(&self) -> u32 {
+        2
+    }
+}
+
+/// Velocity Verlet. Algebraically equivalent to [`Leapfrog`], but written in
+/// terms of position and the average of the old and new accelerations.
+pub struct VelocityVerlet;
+
+impl Integrator for VelocityVerlet {
+    fn step(&self, bodies: &mut [Body], dt: Time, forces: &mut ForceFn) {
+        let mut f_old = vec![Force::ZERO; bodies.len()];
+        forces(bodies, &mut f_old);
+
+        for (body, f) in bodies.iter_mut().zip(&f_old) {
+            let acc = *f / body.mass;
+            body.pos.0 += body.vel * dt + 0.5 * acc.0 * dt.0 * dt.0;
+        }
+
+        let mut f_new = vec![Force::ZERO; bodies.len()];
+        forces(bodies, &mut f_new);
+
+        for ((body, &f0), &f1) in bodies.iter_mut().zip(&f_old).zip(&f_new) {
+            body.vel += (f0 + f1) / body.mass * Time(0.5 * dt.0);
+        }
+    }
+
+    fn order(&self) -> u32 {
+        2
+    }
+}
+
+/// Classical fourth order Runge-Kutta. Accurate over short spans, but not
+/// symplectic, so energy still drifts slowly.
+pub struct Rk4;
+
+impl Integrator for Rk4 {
+    fn step(&self, bodies: &mut [Body], dt: Time, forces: &mut ForceFn) {
+        let n = bodies.len();
+        let start = bodies.to_vec();
+        let mut trial = start.clone();
+        let mut f = vec![Force::ZERO; n];
+
+        // Weighted sums of the stage derivatives.
+        let mut sum_vel = vec![Velocity::ZERO; n];
+        let mut sum_acc = vec![Acceleration::ZERO; n];
+
+        for (stage, weight) in [(0.0, 1.0), (0.5, 2.0), (0.5, 2.0), (1.0, 1.0)] {
+            if stage > 0.0 {
+                let h = Time(stage * dt.0);
+                for (i, body) in trial.iter_mut().enumerate() {
+                    // `f` and `trial` still hold the previous stage here.
+                    let k_vel = body.vel;
+                    let k_acc = f[i] / body.mass;
+                    body.pos.0 = start[i].pos.0 + k_vel * h;
+                    body.vel = start[i].vel + k_acc * h;
+                }
+            }
+
+            forces(&trial, &mut f);
+
+            for (i, body) in trial.iter().enumerate() {
+                sum_vel[i] += weight * body.vel;
+                sum_acc[i] += weight * (f[i] / body.mass);
+            }
+        }
+
+        let sixth_dt = Time(dt.0 / 6.0);
+        for (i, body) in bodies.iter_mut().enumerate() {
+            body.pos.0 = start[i].pos.0 + sum_vel[i] * sixth_dt;
+            body.vel = start[i].vel + sum_acc[i] * sixth_dt;
+        }
+    }
+
+    fn order(&self) -> u32 {
+        4
+    }
+}
+
+/// Yoshida's fourth order symplectic integrator: three leapfrog-style kicks with
+/// carefully chosen (partly negative) sub-step weights.
+pub struct Yoshida4;
+
+#[allow(clippy::excessive_precision)]
+impl Yoshida4 {
+    const W1: Real = 1.351_207_191_959_657_8; // 1 / (2 - 2^(1/3))
+    const W0: Real = -1.702_414_383_919_315_3; // -2^(1/3) / (2 - 2^(1/3))
+    const DRIFTS: [Real; 4] = [
+        0.5 * Self::W1,
+        0.5 * (Self::W0 + Self::W1),
+        0.5 * (Self::W0 + Self::W1),
+        0.5 * Self::W1,
+    ];
+    const KICKS: [Real; 3] = [Self::W1, Self::W0, Self::W1];
+}
+
+impl Integrator for Yoshida4 {
+    fn step(&self, bodies: &mut [Body], dt: Time, forces: &mut ForceFn) {
+        let mut f = vec![Force::ZERO; bodies.len()];
+
+        for (i, &c) in Self::DRIFTS.iter().enumerate() {
+            drift(bodies, Time(c * dt.0));
+            if let Some(&d) = Self::KICKS.get(i) {
+                forces(bodies, &mut f);
+                kick(bodies, &f, Time(d * dt.0));
+            }
+        }
+    }
+
+    fn order(&self) -> u32 {
+        4
+    }
+}
+
+fn kick(bodies: &mut [Body], forces: &[Force], dt: Time) {
+    for (body, &f) in bodies.iter_mut().zip(forces) {
+        body.vel += f / body.mass * dt;
+    }
+}
+
+fn drift(bodies: &mut [Body], dt: Time) {
+    for body in bodies {
+        body.pos.0 += body.vel * dt;
+    }
+}
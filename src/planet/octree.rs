@@ -0,0 +1,215 @@
+use bevy::prelude::*;
+
+use crate::components::{Force, Real, RealVec3};
+
+use super::{gravity, integrator::Body, Constants};
+
+/// Cells this deep are never split, so bodies sitting on top of each other
+/// can't recurse forever. They share a leaf and interact directly instead.
+const MAX_DEPTH: u32 = 24;
+
+/// A Barnes–Hut octree. Every cell knows the total mass and center of mass of
+/// the bodies inside it, so far-away groups of bodies can be treated as one
+/// point mass.
+pub struct Octree {
+    nodes: Vec<Node>,
+}
+
+struct Node {
+    center: RealVec3,
+    half_size: Real,
+    mass: Real,
+    /// Holds the mass-weighted sum of positions until [`Octree::build`]
+    /// divides it through by `mass`.
+    center_of_mass: RealVec3,
+    /// Index of the first of this cell's eight consecutive children.
+    children: Option<usize>,
+    /// Bodies stored directly in this leaf. More than one only at `MAX_DEPTH`.
+    bodies: Vec<usize>,
+}
+
+impl Node {
+    fn new(center: RealVec3, half_size: Real) -> Self {
+        Self {
+            center,
+            half_size,
+            mass: 0.0,
+            center_of_mass: RealVec3::ZERO,
+            children: None,
+            bodies: vec![],
+        }
+    }
+
+    fn octant(&self, pos: RealVec3) -> usize {
+        (pos.x >= self.center.x) as usize
+            | ((pos.y >= self.center.y) as usize) << 1
+            | ((pos.z >= self.center.z) as usize) << 2
+    }
+
+    fn contains(&self, pos: RealVec3) -> bool {
+        (pos - self.center).abs().max_element() <= self.half_size
+    }
+}
+
+impl Octree {
+    pub fn build(bodies: &[Body]) -> Self {
+        let (min, max) = bodies.iter().fold(
+            (RealVec3::splat(Real::MAX), RealVec3::splat(Real::MIN)),
+            |(min, max), body| (min.min(body.pos.0), max.max(body.pos.0)),
+        );
+
+        let (center, half_size) = if bodies.is_empty() {
+            (RealVec3::ZERO, 1.0)
+        } else {
+            (
+                (min + max) / 2.0,
+                ((max - min).max_element() / 2.0).max(1.0),
+            )
+        };
+
+        let mut tree = Self {
+            nodes: vec![Node::new(center, half_size)],
+        };
+
+        for i in 0..bodies.len() {
+            tree.insert(i, bodies);
+        }
+
+        for node in &mut tree.nodes {
+            if node.mass > 0.0 {
+                node.center_of_mass /= node.mass;
+            }
+        }
+
+        tree
+    }
+
+    fn insert(&mut self, i: usize, bodies: &[Body]) {
+        let (pos, mass) = (bodies[i].pos.0, bodies[i].mass.0);
+        let mut idx = 0;
+        let mut depth = 0;
+
+        loop {
+            let node = &mut self.nodes[idx];
+            node.mass += mass;
+            node.center_of_mass += mass * pos;
+
+            if let Some(first) = node.children {
+                idx = first + node.octant(pos);
+                depth += 1;
+                continue;
+            }
+
+            if node.bodies.is_empty() || depth >= MAX_DEPTH {
+                node.bodies.push(i);
+                return;
+            }
+
+            // Occupied leaf: split it, push its resident down a level, then keep
+            // descending with the new body.
+            let residents = std::mem::take(&mut node.bodies);
+            let first = self.split(idx);
+            for j in residents {
+                let (pos_j, mass_j) = (bodies[j].pos.0, bodies[j].mass.0);
+                let octant = self.nodes[idx].octant(pos_j);
+                let child = &mut self.nodes[first + octant];
+                child.mass += mass_j;
+                child.center_of_mass += mass_j * pos_j;
+                child.bodies.push(j);
+            }
+
+            idx = first + self.nodes[idx].octant(pos);
+            depth += 1;
+        }
+    }
+
+    fn split(&mut self, idx: usize) -> usize {
+        let first = self.nodes.len();
+        let Node {
+            center, half_size, ..
+        } = self.nodes[idx];
+        let quarter = half_size / 2.0;
+
+        for octant in 0..8 {
+            let offset = RealVec3::new(
+                if octant & 1 != 0 { quarter } else { -quarter },
+                if octant & 2 != 0 { quarter } else { -quarter },
+                if octant & 4 != 0 { quarter } else { -quarter },
+            );
+            self.nodes.push(Node::new(center + offset, quarter));
+        }
+
+        self.nodes[idx].children = Some(first);
+        first
+    }
+
+    /// Approximate gravitational forces on every body. A cell is treated as a
+    /// single point mass when `cell width / distance < constants.opening_angle`.
+    pub fn forces(&self, bodies: &[Body], constants: &Constants, forces: &mut [Force]) {
+        for (i, force) in forces.iter_mut().enumerate() {
+            *force = self.force_on(i, bodies, constants);
+        }
+    }
+
+    fn force_on(&self, i: usize, bodies: &[Body], constants: &Constants) -> Force {
+        let body = &bodies[i];
+        let theta_sq = constants.opening_angle * constants.opening_angle;
+        let mut total = RealVec3::ZERO;
+        let mut stack = vec![0];
+
+        while let Some(idx) = stack.pop() {
+            let node = &self.nodes[idx];
+            if node.mass <= 0.0 {
+                continue;
+            }
+
+            let Some(first) = node.children else {
+                for &j in node.bodies.iter().filter(|&&j| j != i) {
+                    if let Some(force) = gravity::pair_force(body, &bodies[j], constants) {
+                        total += force;
+                    }
+                }
+                continue;
+            };
+
+            let to_com = node.center_of_mass - body.pos.0;
+            let width = 2.0 * node.half_size;
+
+            if !node.contains(body.pos.0) && width * width < theta_sq * to_com.length_squared() {
+                total += gravity::attraction(constants, body.mass.0, node.mass, to_com);
+            } else {
+                stack.extend(first..first + 8);
+            }
+        }
+
+        Force(total)
+    }
+
+    /// Center and half-width of every non-empty cell.
+    pub fn cells(&self) -> impl Iterator<Item = (RealVec3, Real)> + '_ {
+        self.nodes
+            .iter()
+            .filter(|node| node.mass > 0.0)
+            .map(|node| (node.center, node.half_size))
+    }
+}
+
+/// Debug overlay which draws the cells of the most recently built octree.
+#[derive(Resource, Default)]
+pub struct OctreeOverlay {
+    pub enabled: bool,
+    pub cells: Vec<(Vec3, f32)>,
+}
+
+pub fn octree_overlay_system(overlay: Res<OctreeOverlay>, mut gizmos: Gizmos) {
+    if !overlay.enabled {
+        return;
+    }
+
+    for &(center, half_size) in &overlay.cells {
+        gizmos.cuboid(
+            Transform::from_translation(center).with_scale(Vec3::splat(2.0 * half_size)),
+            Color::rgba(0.3, 0.8, 0.4, 0.25),
+        );
+    }
+}
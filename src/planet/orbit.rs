@@ -0,0 +1,398 @@
+//! Keplerian orbital elements, and conversions between them and state
+//! vectors.
+//!
+//! Elements are measured against the ecliptic, the XZ plane. An orbit going
+//! the same way as the planets spawned on circular orbits has an inclination
+//! under 90°, and longitudes are measured from the +X axis in that direction.
+
+use bevy::prelude::*;
+use bevy_inspector_egui::{prelude::ReflectInspectorOptions, InspectorOptions};
+
+use crate::components::{consts, Mass, Position, Real, RealVec3, Velocity};
+
+use super::{
+    disk::Star,
+    simulation::{PhysicsSet, SimulationStep},
+    stars::orbit_center,
+    Constants, Planet,
+};
+
+pub struct OrbitPlugin;
+
+impl Plugin for OrbitPlugin {
+    fn build(&self, app: &mut App) {
+        app // <no autoformat>
+            .register_type::<OrbitalElements>()
+            .add_systems(
+                SimulationStep,
+                orbital_elements_system.in_set(PhysicsSet::Diagnostics),
+            );
+    }
+}
+
+/// The orbit of a body about its primary, as of the last substep. Angles are
+/// in radians.
+#[derive(Component, Reflect, InspectorOptions, Default, Debug, Clone, Copy, PartialEq)]
+#[reflect(InspectorOptions)]
+pub struct OrbitalElements {
+    /// a. Negative for hyperbolic orbits.
+    pub semi_major_axis: Real,
+    /// e. Below 1 the orbit is bound.
+    #[inspector(min = 0.0)]
+    pub eccentricity: Real,
+    /// i, between the orbit and the ecliptic.
+    pub inclination: Real,
+    /// Ω, from +X to where the body rises through the ecliptic. Zero if the
+    /// orbit lies in the ecliptic.
+    pub longitude_of_ascending_node: Real,
+    /// ω, from the ascending node to periapsis. For an orbit in the ecliptic
+    /// it's measured from +X instead, and it's zero for a circular orbit.
+    pub argument_of_periapsis: Real,
+    /// ν, from periapsis to the body. For a circular orbit it's measured from
+    /// where ω would be.
+    pub true_anomaly: Real,
+    /// M, which grows steadily with time.
+    pub mean_anomaly: Real,
+}
+
+/// Orbits closer than this to circular, or to the ecliptic, are taken to be
+/// exactly so, as their periapsis or node is lost in rounding.
+const DEGENERATE_TOLERANCE: Real = 1e-6;
+
+impl OrbitalElements {
+    /// Elements of a body at `pos` moving at `vel`, both relative to its
+    /// primary, where `mu` is G times the mass of the body and its primary.
+    pub fn from_state(mu: Real, pos: RealVec3, vel: RealVec3) -> Self {
+        let r = to_ecliptic_frame(pos);
+        let v = to_ecliptic_frame(vel);
+
+        let h = r.cross(v);
+        // A body falling straight in has no plane; call it the ecliptic.
+        let h_hat = h.try_normalize().unwrap_or(RealVec3::Z);
+        let node = RealVec3::Z.cross(h);
+        let e_vec = v.cross(h) / mu - r / r.length();
+        let e = e_vec.length();
+
+        let energy = 0.5 * v.length_squared() - mu / r.length();
+        let a = -mu / (2.0 * energy);
+
+        let equatorial = node.length() <= DEGENERATE_TOLERANCE * h.length();
+        let circular = e <= DEGENERATE_TOLERANCE;
+
+        // Angles within the orbit are measured around h, so that they increase
+        // in the direction the body moves.
+        let angle = |from: RealVec3, to: RealVec3| {
+            Real::atan2(h_hat.dot(from.cross(to)), from.dot(to)).rem_euclid(consts::TAU)
+        };
+
+        let reference = if equatorial { RealVec3::X } else { node };
+        let periapsis = if circular { reference } else { e_vec };
+        let true_anomaly = angle(periapsis, r);
+
+        Self {
+            semi_major_axis: a,
+            eccentricity: e,
+            inclination: h_hat.z.clamp(-1.0, 1.0).acos(),
+            longitude_of_ascending_node: if equatorial {
+                0.0
+            } else {
+                Real::atan2(node.y, node.x).rem_euclid(consts::TAU)
+            },
+            argument_of_periapsis: if circular {
+                0.0
+            } else {
+                angle(reference, e_vec)
+            },
+            true_anomaly,
+            mean_anomaly: mean_anomaly(true_anomaly, e),
+        }
+    }
+
+    /// Elements with the body `mean_anomaly` into its orbit.
+    pub fn from_mean_anomaly(
+        semi_major_axis: Real,
+        eccentricity: Real,
+        inclination: Real,
+        longitude_of_ascending_node: Real,
+        argument_of_periapsis: Real,
+        mean_anomaly: Real,
+    ) -> Self {
+        Self {
+            semi_major_axis,
+            eccentricity,
+            inclination,
+            longitude_of_ascending_node,
+            argument_of_periapsis,
+            true_anomaly: true_anomaly(mean_anomaly, eccentricity),
+            mean_anomaly,
+        }
+    }
+
+    /// Position and velocity of the body relative to its primary, where `mu`
+    /// is G times the mass of the body and its primary. The true anomaly is
+    /// taken as where the body is.
+    pub fn to_state(self, mu: Real) -> (RealVec3, RealVec3) {
+        let e = self.eccentricity;
+        let (sin_nu, cos_nu) = self.true_anomaly.sin_cos();
+
+        let p = self.semi_major_axis * (1.0 - e * e);
+        let r = p / (1.0 + e * cos_nu);
+        let pos = RealVec3::new(r * cos_nu, r * sin_nu, 0.0);
+        let vel = (mu / p).sqrt() * RealVec3::new(-sin_nu, e + cos_nu, 0.0);
+
+        let orient = |v: RealVec3| {
+            let v = rotate_z(v, self.argument_of_periapsis);
+            let v = rotate_x(v, self.inclination);
+            from_ecliptic_frame(rotate_z(v, self.longitude_of_ascending_node))
+        };
+
+        (orient(pos), orient(vel))
+    }
+
+    /// Where the body is when at true anomaly `true_anomaly`, relative to its
+    /// primary. Used to trace out the orbit.
+    pub fn position_at(&self, true_anomaly: Real) -> RealVec3 {
+        let elements = Self {
+            true_anomaly,
+            ..*self
+        };
+        // The shape of the orbit doesn't depend on how fast it's gone round.
+        elements.to_state(1.0).0
+    }
+}
+
+/// The ecliptic is the XZ plane with +Y up, and prograde orbits go clockwise
+/// seen from above. Taking (X, -Z, Y) as (x, y, z) gives the usual frame, with
+/// the reference plane in xy and prograde orbits going round +z.
+fn to_ecliptic_frame(v: RealVec3) -> RealVec3 {
+    RealVec3::new(v.x, -v.z, v.y)
+}
+
+fn from_ecliptic_frame(v: RealVec3) -> RealVec3 {
+    RealVec3::new(v.x, v.z, -v.y)
+}
+
+fn rotate_z(v: RealVec3, angle: Real) -> RealVec3 {
+    let (sin, cos) = angle.sin_cos();
+    RealVec3::new(cos * v.x - sin * v.y, sin * v.x + cos * v.y, v.z)
+}
+
+fn rotate_x(v: RealVec3, angle: Real) -> RealVec3 {
+    let (sin, cos) = angle.sin_cos();
+    RealVec3::new(v.x, cos * v.y - sin * v.z, sin * v.y + cos * v.z)
+}
+
+/// Mean anomaly of a body at true anomaly `nu` on an orbit of eccentricity
+/// `e`. Within [0, 2π) for bound orbits.
+pub fn mean_anomaly(nu: Real, e: Real) -> Real {
+    let (sin_nu, cos_nu) = nu.sin_cos();
+    if e < 1.0 {
+        let ecc_anomaly = Real::atan2((1.0 - e * e).sqrt() * sin_nu, e + cos_nu);
+        (ecc_anomaly - e * ecc_anomaly.sin()).rem_euclid(consts::TAU)
+    } else {
+        let hyp_anomaly = ((e * e - 1.0).sqrt() * sin_nu / (1.0 + e * cos_nu)).asinh();
+        e * hyp_anomaly.sinh() - hyp_anomaly
+    }
+}
+
+/// True anomaly of a body at mean anomaly `m` on an orbit of eccentricity `e`.
+pub fn true_anomaly(m: Real, e: Real) -> Real {
+    if e < 1.0 {
+        let ecc_anomaly = eccentric_anomaly(m, e);
+        let (sin_e, cos_e) = ecc_anomaly.sin_cos();
+        Real::atan2((1.0 - e * e).sqrt() * sin_e, cos_e - e).rem_euclid(consts::TAU)
+    } else {
+        let hyp_anomaly = hyperbolic_anomaly(m, e);
+        let (sinh_h, cosh_h) = (hyp_anomaly.sinh(), hyp_anomaly.cosh());
+        Real::atan2((e * e - 1.0).sqrt() * sinh_h, e - cosh_h).rem_euclid(consts::TAU)
+    }
+}
+
+/// Most iterations the Kepler solvers take. Bisection alone would reach full
+/// precision well within this.
+const MAX_KEPLER_ITERATIONS: usize = 100;
+
+/// Solves Kepler's equation M = E - e sin E for the eccentric anomaly E of an
+/// elliptic orbit.
+pub fn eccentric_anomaly(m: Real, e: Real) -> Real {
+    // Reduce M to [-π, π), where E - M = e sin E has the sign of M.
+    let turns = ((m + consts::PI) / consts::TAU).floor() * consts::TAU;
+    let m = m - turns;
+
+    // Starting guess from Danby (1987).
+    let guess = m + 0.85 * e * m.signum();
+    let (lo, hi) = if m >= 0.0 { (m, m + e) } else { (m - e, m) };
+
+    let ecc_anomaly =
+        safeguarded_newton(|x| (x - e * x.sin() - m, 1.0 - e * x.cos()), guess, lo, hi);
+    ecc_anomaly + turns
+}
+
+/// Solves the hyperbolic Kepler equation M = e sinh H - H for the hyperbolic
+/// anomaly H of an unbound orbit.
+pub fn hyperbolic_anomaly(m: Real, e: Real) -> Real {
+    // The equation is odd in H, so solve it for |M|. Since sinh H ≥ H there,
+    // (e - 1) sinh H ≤ |M| ≤ e sinh H.
+    let abs_m = m.abs();
+    let lo = (abs_m / e).asinh();
+    let hi = (abs_m / (e - 1.0)).asinh();
+    let guess = (2.0 * abs_m / e + 1.8).ln();
+
+    let hyp_anomaly = safeguarded_newton(
+        |x| (e * x.sinh() - x - abs_m, e * x.cosh() - 1.0),
+        guess,
+        lo,
+        hi,
+    );
+    hyp_anomaly.copysign(m)
+}
+
+/// Finds the root in `[lo, hi]` of an increasing function, given as its value
+/// and derivative, by Newton's method from `guess`. Steps which would leave
+/// the bracket bisect it instead, so this always converges.
+fn safeguarded_newton(
+    f: impl Fn(Real) -> (Real, Real),
+    guess: Real,
+    mut lo: Real,
+    mut hi: Real,
+) -> Real {
+    let mut x = guess.clamp(lo, hi);
+
+    for _ in 0..MAX_KEPLER_ITERATIONS {
+        let (value, slope) = f(x);
+        if value == 0.0 {
+            return x;
+        }
+        if value > 0.0 {
+            hi = x;
+        } else {
+            lo = x;
+        }
+
+        let newton = x - value / slope;
+        let next = if newton > lo && newton < hi {
+            newton
+        } else {
+            0.5 * (lo + hi)
+        };
+
+        if (next - x).abs() <= 4.0 * Real::EPSILON * x.abs().max(1.0) {
+            return next;
+        }
+        x = next;
+    }
+
+    x
+}
+
+type OrbitingPlanetsData<'a, 'b, 'c, 'd, 'e> = (
+    Entity,
+    &'a Position,
+    &'b Velocity,
+    &'c Mass,
+    &'d mut OrbitalElements,
+);
+
+/// Updates every body's orbital elements about whatever it orbits. A star
+/// orbits the others, and a lone star keeps its elements as they were.
+fn orbital_elements_system(
+    mut planets: Query<OrbitingPlanetsData, With<Planet>>,
+    stars: Query<(Entity, &Position, &Velocity, &Mass), With<Star>>,
+    constants: Res<Constants>,
+) {
+    for (entity, pos, vel, mass, mut elements) in &mut planets {
+        let others = stars
+            .iter()
+            .filter(|&(star, ..)| star != entity)
+            .map(|(_, pos, vel, mass)| (pos, vel, mass));
+        let Some(center) = orbit_center(others, pos.0) else {
+            continue;
+        };
+
+        let mu = constants.grav_const * (center.mass + *mass).0;
+        *elements = OrbitalElements::from_state(mu, pos.0 - center.pos, (*vel - center.vel).0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: RealVec3, b: RealVec3) {
+        assert!((a - b).length() < 1e-3 * b.length(), "{a} != {b}");
+    }
+
+    #[test]
+    fn state_round_trips_through_elements() {
+        let mu = 20_000.0;
+        let states = [
+            // Circular in the ecliptic, going the way spawned planets do.
+            (
+                RealVec3::new(100.0, 0.0, 0.0),
+                RealVec3::new(0.0, 0.0, -14.14),
+            ),
+            // Eccentric and inclined.
+            (
+                RealVec3::new(80.0, 20.0, -30.0),
+                RealVec3::new(3.0, 4.0, -15.0),
+            ),
+            // Retrograde.
+            (
+                RealVec3::new(-50.0, 5.0, 120.0),
+                RealVec3::new(-10.0, 1.0, 5.0),
+            ),
+            // Unbound.
+            (
+                RealVec3::new(60.0, -10.0, 40.0),
+                RealVec3::new(-5.0, 8.0, -30.0),
+            ),
+        ];
+
+        for (pos, vel) in states {
+            let elements = OrbitalElements::from_state(mu, pos, vel);
+            let (new_pos, new_vel) = elements.to_state(mu);
+            assert_close(new_pos, pos);
+            assert_close(new_vel, vel);
+
+            let again = OrbitalElements::from_mean_anomaly(
+                elements.semi_major_axis,
+                elements.eccentricity,
+                elements.inclination,
+                elements.longitude_of_ascending_node,
+                elements.argument_of_periapsis,
+                elements.mean_anomaly,
+            );
+            let diff = again.true_anomaly - elements.true_anomaly;
+            assert!(((diff + consts::PI).rem_euclid(consts::TAU) - consts::PI).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn spawned_orbits_are_prograde() {
+        let elements = OrbitalElements::from_state(
+            20_000.0,
+            RealVec3::new(0.0, 0.0, 100.0),
+            RealVec3::new(14.14, 0.0, 0.0),
+        );
+        assert!(elements.inclination < 1e-3);
+        assert!(elements.eccentricity < 1e-3);
+    }
+
+    #[test]
+    fn kepler_solvers_converge_for_extreme_orbits() {
+        for e in [0.0, 0.5, 0.99, 0.999999] {
+            for m in [-7.0, -1e-4, 0.0, 0.1, 3.1, consts::PI, 10.0] {
+                let ecc_anomaly = eccentric_anomaly(m, e);
+                assert!((ecc_anomaly - e * ecc_anomaly.sin() - m).abs() < 1e-4);
+            }
+        }
+
+        for e in [1.000001, 1.5, 10.0] {
+            for m in [-50.0, -1e-4, 0.0, 0.1, 3.0, 1000.0] {
+                let hyp_anomaly = hyperbolic_anomaly(m, e);
+                let residual = e * hyp_anomaly.sinh() - hyp_anomaly - m;
+                assert!(residual.abs() < 1e-4 * m.abs().max(1.0));
+            }
+        }
+    }
+}
@@ -0,0 +1,507 @@
+//! Collision outcomes for gravity-dominated bodies, after Leinhardt & Stewart
+//! (2012), "Collisions between gravity-dominated bodies. I. Outcome regimes and
+//! scaling laws".
+
+use crate::components::{consts, Mass, Radius, Real, RealVec3, Velocity};
+
+use super::{collisions::PlanetInfo, composition::Composition, radius_from_mass, Constants};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CollisionModel {
+    /// Every collision is a perfect merger.
+    #[default]
+    PerfectMerge,
+    /// The outcome of a two-body impact is picked by [`classify`]. Pileups of
+    /// three or more bodies still merge.
+    LeinhardtStewart,
+}
+
+impl CollisionModel {
+    pub const ALL: [Self; 2] = [Self::PerfectMerge, Self::LeinhardtStewart];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::PerfectMerge => "Perfect Merge",
+            Self::LeinhardtStewart => "Leinhardt & Stewart",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    /// Both bodies become one.
+    Merge,
+    /// The target grows to `largest_remnant`; the rest of the projectile is
+    /// thrown off as fragments.
+    PartialAccretion { largest_remnant: Mass },
+    /// The projectile skims past the target and carries on, eroded down to
+    /// [`hit_and_run_remnant`]. The target is left as it was.
+    HitAndRun,
+    /// The bodies rebound off each other, losing part of their approach speed
+    /// according to [`Constants::restitution`].
+    Bounce,
+    /// The target is eroded down to `largest_remnant`, and everything else is
+    /// thrown off as fragments.
+    Fragmentation { largest_remnant: Mass },
+}
+
+/// Material parameter μ̄ controlling how the disruption threshold depends on
+/// the impact speed. 0.36 is the value fitted for weak aggregates.
+const MU_BAR: Real = 0.36;
+
+/// Slope β of the cumulative fragment distribution N(>m) ∝ m^-β.
+const FRAGMENT_SLOPE: Real = 2.85;
+
+/// Fragments leave the impact site this much faster than the mutual escape
+/// velocity.
+const EJECTION_SPEED_FACTOR: Real = 1.05;
+
+/// Grazes too slow to rebound by at least this fraction of the mutual escape
+/// velocity settle into a merger instead (L&S's graze-and-merge).
+const MIN_BOUNCE_SPEED_FRACTION: Real = 0.05;
+
+/// The quantities an outcome is chosen from.
+#[derive(Debug, Clone, Copy)]
+pub struct Impact {
+    /// Speed of the projectile relative to the target.
+    pub speed: Real,
+    /// Sine of the impact angle: 0 for a head-on hit, 1 for a perfect graze.
+    pub b: Real,
+    /// Unit vector from the target's center toward the projectile's.
+    pub normal: RealVec3,
+    /// Mutual escape velocity at contact.
+    pub escape_speed: Real,
+}
+
+impl Impact {
+    pub fn new(constants: &Constants, target: &PlanetInfo, projectile: &PlanetInfo) -> Self {
+        let rel_pos = projectile.pos - target.pos;
+        let rel_vel = (projectile.vel - target.vel).0;
+        let total_mass = target.mass + projectile.mass;
+        let radii_sum = target.radius + projectile.radius;
+
+        Self {
+            speed: rel_vel.length(),
+            b: rel_pos
+                .normalize_or_zero()
+                .cross(rel_vel.normalize_or_zero())
+                .length(),
+            normal: rel_pos.normalize_or_zero(),
+            escape_speed: (2.0 * constants.grav_const * total_mass.0 / radii_sum.0).sqrt(),
+        }
+    }
+
+    /// Speed at which the bodies approach each other along the line of
+    /// centers. Negative if they are already separating.
+    pub fn approach_speed(&self, target: &PlanetInfo, projectile: &PlanetInfo) -> Real {
+        -(projectile.vel - target.vel).0.dot(self.normal)
+    }
+}
+
+/// Picks the outcome of `projectile` striking the more massive `target`.
+/// Returns `None` if the two are overlapping but already moving apart.
+pub fn classify(
+    constants: &Constants,
+    target: &PlanetInfo,
+    projectile: &PlanetInfo,
+) -> Option<Outcome> {
+    let impact = Impact::new(constants, target, projectile);
+    let approach_speed = impact.approach_speed(target, projectile);
+    if approach_speed <= 0.0 {
+        return None;
+    }
+
+    let b_crit = target.radius.0 / (target.radius + projectile.radius).0;
+    let grazing = impact.b > b_crit;
+
+    if impact.speed < impact.escape_speed {
+        let rebound = constants.restitution * approach_speed;
+        return if grazing && rebound > MIN_BOUNCE_SPEED_FRACTION * impact.escape_speed {
+            Some(Outcome::Bounce)
+        } else {
+            Some(Outcome::Merge)
+        };
+    }
+
+    let largest_remnant = largest_remnant(constants, target, projectile, &impact);
+
+    Some(if largest_remnant.0 >= target.mass.0 {
+        if grazing {
+            Outcome::HitAndRun
+        } else {
+            Outcome::PartialAccretion { largest_remnant }
+        }
+    } else {
+        Outcome::Fragmentation { largest_remnant }
+    })
+}
+
+/// Mass of the largest body left after the impact, from the universal law for
+/// the catastrophic disruption threshold Q*_RD.
+pub fn largest_remnant(
+    constants: &Constants,
+    target: &PlanetInfo,
+    projectile: &PlanetInfo,
+    impact: &Impact,
+) -> Mass {
+    let (m_t, m_p) = (target.mass.0, projectile.mass.0);
+    let m_tot = m_t + m_p;
+    let reduced_mass = m_t * m_p / m_tot;
+
+    // Specific impact energy in the center-of-mass frame.
+    let q_r = 0.5 * reduced_mass * impact.speed * impact.speed / m_tot;
+
+    // Threshold for an equal-mass, head-on impact: a fixed multiple of the
+    // gravitational binding energy per unit mass of the combined body.
+    let combined = Composition::mix([
+        (target.mass, target.composition),
+        (projectile.mass, projectile.composition),
+    ]);
+    let r_c1 = radius_from_mass(Mass(m_tot), combined.density(&constants.densities)).0;
+    let q_rd_equal = constants.disruption_strength * 0.6 * constants.grav_const * m_tot / r_c1;
+
+    // Correct for unequal masses...
+    let gamma = m_p / m_t;
+    let q_rd =
+        q_rd_equal * ((1.0 + gamma).powi(2) / (4.0 * gamma)).powf(2.0 / (3.0 * MU_BAR) - 1.0);
+
+    // ...and for only part of the projectile hitting the target in an oblique
+    // impact.
+    let interacting = interacting_fraction(projectile.radius, target, projectile, impact);
+    let interacting_reduced_mass = interacting * m_t * m_p / (interacting * m_p + m_t);
+    let q_rd = q_rd * (reduced_mass / interacting_reduced_mass).powf(2.0 - 1.5 * MU_BAR);
+
+    let ratio = q_r / q_rd;
+    if ratio < 1.8 {
+        Mass(m_tot * (1.0 - 0.5 * ratio))
+    } else {
+        // Super-catastrophic regime.
+        Mass(m_tot * 0.1 * (ratio / 1.8).powf(-1.5))
+    }
+}
+
+/// Fraction of the body of radius `radius` which overlaps the other body's
+/// path in an oblique impact: the cap reaching `(R_t + R_p)(1 - b)` into it.
+fn interacting_fraction(
+    radius: Radius,
+    target: &PlanetInfo,
+    projectile: &PlanetInfo,
+    impact: &Impact,
+) -> Real {
+    let r = radius.0;
+    let overlap = (target.radius + projectile.radius).0 * (1.0 - impact.b);
+    if overlap < 2.0 * r {
+        (3.0 * r * overlap * overlap - overlap.powi(3)) / (4.0 * r.powi(3))
+    } else {
+        1.0
+    }
+}
+
+/// Mass the projectile keeps after a hit-and-run. L&S run the impact the
+/// other way round: the projectile is struck head-on by the part of the target
+/// it cut through. It never comes away heavier than it arrived.
+pub fn hit_and_run_remnant(
+    constants: &Constants,
+    target: &PlanetInfo,
+    projectile: &PlanetInfo,
+    impact: &Impact,
+) -> Mass {
+    let interacting = interacting_fraction(target.radius, target, projectile, impact);
+
+    // The slab of target is a column as wide as the projectile, so all of it
+    // takes part.
+    let slab = PlanetInfo {
+        mass: Mass(interacting * target.mass.0),
+        radius: projectile.radius,
+        ..*target
+    };
+    let head_on = Impact { b: 0.0, ..*impact };
+
+    let remnant = largest_remnant(constants, projectile, &slab, &head_on);
+    Mass(remnant.0.min(projectile.mass.0))
+}
+
+/// Splits `debris` into at most [`Constants::fragment_count`] fragments whose
+/// masses follow N(>m) ∝ m^-β, none lighter than
+/// [`Constants::min_fragment_mass`]. Returns no fragments if there isn't even
+/// enough debris for one.
+pub fn fragment_masses(constants: &Constants, debris: Mass) -> Vec<Mass> {
+    for n in (1..=constants.fragment_count).rev() {
+        let weights = (1..=n)
+            .map(|k| (k as Real).powf(-1.0 / FRAGMENT_SLOPE))
+            .collect::<Vec<_>>();
+        let total = weights.iter().sum::<Real>();
+        let masses = weights
+            .iter()
+            .map(|w| Mass(debris.0 * w / total))
+            .collect::<Vec<_>>();
+
+        if masses
+            .last()
+            .is_some_and(|m| m.0 >= constants.min_fragment_mass)
+        {
+            return masses;
+        }
+    }
+
+    vec![]
+}
+
+/// Velocities after a rebound in which the approach speed along `normal` is
+/// reversed and scaled by [`Constants::restitution`].
+pub fn bounce(
+    constants: &Constants,
+    target: &PlanetInfo,
+    projectile: &PlanetInfo,
+    normal: RealVec3,
+) -> (Velocity, Velocity) {
+    let total_mass = target.mass + projectile.mass;
+    let v_com = (target.mass * target.vel + projectile.mass * projectile.vel) / total_mass;
+
+    let rel_vel = (projectile.vel - target.vel).0;
+    let normal_vel = rel_vel.dot(normal) * normal;
+    let new_rel_vel = rel_vel - (1.0 + constants.restitution) * normal_vel;
+
+    (
+        v_com - Velocity(projectile.mass.0 / total_mass.0 * new_rel_vel),
+        v_com + Velocity(target.mass.0 / total_mass.0 * new_rel_vel),
+    )
+}
+
+/// Positions and velocities for a largest remnant and its fragments, flung out
+/// in evenly spread directions at just over the escape speed. The directions
+/// are turned by `twist` radians about `normal` so that repeated impacts don't
+/// all eject along the same lines. Every body is taken to have the given
+/// `density`. The total mass, momentum and center of mass of the input are
+/// kept.
+#[allow(clippy::too_many_arguments)]
+pub fn scatter_fragments(
+    center_of_mass: RealVec3,
+    velocity: Velocity,
+    normal: RealVec3,
+    escape_speed: Real,
+    remnant: Mass,
+    fragments: &[Mass],
+    density: Real,
+    twist: Real,
+) -> Vec<(RealVec3, Velocity, Mass)> {
+    let normal = if normal == RealVec3::ZERO {
+        RealVec3::Y
+    } else {
+        normal
+    };
+    let (u, v) = normal.any_orthonormal_pair();
+    let remnant_radius = radius_from_mass(remnant, density).0;
+    let ejection_speed = EJECTION_SPEED_FACTOR * escape_speed;
+
+    // Fibonacci-sphere directions, the first pointing along `normal`.
+    let golden_angle = consts::PI * (3.0 - Real::sqrt(5.0));
+    let n = fragments.len() as Real;
+
+    let mut bodies = vec![(RealVec3::ZERO, RealVec3::ZERO, remnant)];
+    for (k, &mass) in fragments.iter().enumerate() {
+        let z = 1.0 - 2.0 * (k as Real + 0.5) / n;
+        let ring = (1.0 - z * z).sqrt();
+        let phi = twist + golden_angle * k as Real;
+        let dir = z * normal + ring * (phi.cos() * u + phi.sin() * v);
+
+        let dist = (remnant_radius + radius_from_mass(mass, density).0) * 1.05;
+        bodies.push((dist * dir, ejection_speed * dir, mass));
+    }
+
+    // Take out the net offset and kick so that the center of mass and the
+    // total momentum are unchanged.
+    let total_mass = bodies.iter().map(|&(_, _, mass)| mass).sum::<Mass>();
+    let mean_offset = bodies
+        .iter()
+        .map(|&(offset, _, mass)| offset * mass.0)
+        .sum::<RealVec3>()
+        / total_mass.0;
+    let mean_kick = bodies
+        .iter()
+        .map(|&(_, kick, mass)| kick * mass.0)
+        .sum::<RealVec3>()
+        / total_mass.0;
+
+    bodies
+        .into_iter()
+        .map(|(offset, kick, mass)| {
+            (
+                center_of_mass + offset - mean_offset,
+                velocity + Velocity(kick - mean_kick),
+                mass,
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Entity;
+
+    use super::*;
+    use crate::{
+        components::{Momentum, Spin},
+        planet::composition::Densities,
+    };
+
+    fn body(id: u32, mass: Real, pos: RealVec3, vel: RealVec3) -> PlanetInfo {
+        let composition = Composition::default();
+        let radius = radius_from_mass(Mass(mass), composition.density(&Densities::default()));
+        PlanetInfo {
+            entity: Entity::from_raw(id),
+            mass: Mass(mass),
+            vel: Velocity(vel),
+            pos,
+            start: pos,
+            radius,
+            spin: Spin::at_rest(Mass(mass), radius),
+            composition,
+        }
+    }
+
+    /// A target of mass 100 at rest, and a projectile of mass 10 touching it
+    /// at `angle` radians from the x axis and moving along -x at `speed`.
+    fn impact_at(angle: Real, speed: Real) -> (PlanetInfo, PlanetInfo) {
+        let target = body(0, 100.0, RealVec3::ZERO, RealVec3::ZERO);
+        let mut projectile = body(1, 10.0, RealVec3::ZERO, RealVec3::new(-speed, 0.0, 0.0));
+        let dist = (target.radius + projectile.radius).0;
+        projectile.pos = dist * RealVec3::new(angle.cos(), angle.sin(), 0.0);
+        (target, projectile)
+    }
+
+    fn escape_speed(angle: Real) -> Real {
+        let constants = Constants::default();
+        let (target, projectile) = impact_at(angle, 1.0);
+        Impact::new(&constants, &target, &projectile).escape_speed
+    }
+
+    /// Speed above which the target loses mass rather than gaining it. The
+    /// specific impact energy grows as the speed squared, so this follows from
+    /// the remnant at unit speed.
+    fn erosion_speed(angle: Real) -> Real {
+        let constants = Constants::default();
+        let (target, projectile) = impact_at(angle, 1.0);
+        let impact = Impact::new(&constants, &target, &projectile);
+        let m_tot = (target.mass + projectile.mass).0;
+
+        let unit_ratio =
+            2.0 * (1.0 - largest_remnant(&constants, &target, &projectile, &impact).0 / m_tot);
+        let boundary_ratio = 2.0 * projectile.mass.0 / m_tot;
+        (boundary_ratio / unit_ratio).sqrt()
+    }
+
+    fn classify_at(angle: Real, speed: Real) -> Option<Outcome> {
+        let (target, projectile) = impact_at(angle, speed);
+        classify(&Constants::default(), &target, &projectile)
+    }
+
+    const HEAD_ON: Real = 0.0;
+    const GRAZING: Real = consts::FRAC_PI_3;
+
+    #[test]
+    fn slow_impacts_merge_or_bounce() {
+        let escape = escape_speed(GRAZING);
+
+        assert_eq!(classify_at(HEAD_ON, 0.9 * escape), Some(Outcome::Merge));
+        assert_eq!(classify_at(GRAZING, 0.9 * escape), Some(Outcome::Bounce));
+
+        // A graze with too little rebound to get away settles into a merger.
+        assert_eq!(classify_at(GRAZING, 0.1 * escape), Some(Outcome::Merge));
+    }
+
+    #[test]
+    fn separating_bodies_have_no_outcome() {
+        assert_eq!(classify_at(HEAD_ON, -1.0), None);
+    }
+
+    #[test]
+    fn fast_impacts_accrete_below_erosion_speed() {
+        let speed = erosion_speed(HEAD_ON);
+        assert!(speed > escape_speed(HEAD_ON));
+
+        assert!(matches!(
+            classify_at(HEAD_ON, 0.95 * speed),
+            Some(Outcome::PartialAccretion { largest_remnant }) if largest_remnant.0 >= 100.0
+        ));
+        assert!(matches!(
+            classify_at(HEAD_ON, 1.05 * speed),
+            Some(Outcome::Fragmentation { largest_remnant }) if largest_remnant.0 < 100.0
+        ));
+    }
+
+    #[test]
+    fn fast_grazes_hit_and_run_below_erosion_speed() {
+        let speed = erosion_speed(GRAZING);
+        assert!(speed > escape_speed(GRAZING));
+
+        assert_eq!(classify_at(GRAZING, 0.95 * speed), Some(Outcome::HitAndRun));
+        assert!(matches!(
+            classify_at(GRAZING, 1.05 * speed),
+            Some(Outcome::Fragmentation { .. })
+        ));
+    }
+
+    #[test]
+    fn hit_and_run_erodes_the_projectile_more_the_faster_it_goes() {
+        let constants = Constants::default();
+        let remnant_at = |speed| {
+            let (target, projectile) = impact_at(GRAZING, speed);
+            let impact = Impact::new(&constants, &target, &projectile);
+            hit_and_run_remnant(&constants, &target, &projectile, &impact).0
+        };
+
+        // Barely faster than escape, the projectile gets away whole.
+        assert_eq!(remnant_at(1.05 * escape_speed(GRAZING)), 10.0);
+
+        let speed = erosion_speed(GRAZING);
+        assert!(remnant_at(0.6 * speed) < 10.0);
+        assert!(remnant_at(0.95 * speed) < remnant_at(0.6 * speed));
+        assert!(remnant_at(0.95 * speed) > 0.0);
+    }
+
+    #[test]
+    fn remnant_and_fragments_conserve_mass() {
+        let constants = Constants::default();
+
+        for factor in [1.5, 3.0, 10.0] {
+            let (target, projectile) = impact_at(HEAD_ON, factor * erosion_speed(HEAD_ON));
+            let impact = Impact::new(&constants, &target, &projectile);
+            let total = (target.mass + projectile.mass).0;
+
+            let remnant = largest_remnant(&constants, &target, &projectile, &impact);
+            assert!(remnant.0 > 0.0 && remnant.0 < target.mass.0);
+
+            let fragments = fragment_masses(&constants, Mass(total) - remnant);
+            assert!(!fragments.is_empty());
+            assert!(fragments.len() <= constants.fragment_count);
+            assert!(fragments.iter().all(|m| m.0 >= constants.min_fragment_mass));
+
+            let debris = fragments.iter().copied().sum::<Mass>();
+            assert!((remnant.0 + debris.0 - total).abs() < 1e-4 * total);
+        }
+    }
+
+    #[test]
+    fn too_little_debris_makes_no_fragments() {
+        let constants = Constants::default();
+        assert!(fragment_masses(&constants, Mass(0.5 * constants.min_fragment_mass)).is_empty());
+    }
+
+    #[test]
+    fn bounce_conserves_momentum() {
+        let constants = Constants::default();
+        let (target, mut projectile) = impact_at(GRAZING, 5.0);
+        projectile.vel.0.z = 2.0;
+        let impact = Impact::new(&constants, &target, &projectile);
+
+        let (target_v, projectile_v) = bounce(&constants, &target, &projectile, impact.normal);
+
+        let before = target.mass * target.vel + projectile.mass * projectile.vel;
+        let after: Momentum = target.mass * target_v + projectile.mass * projectile_v;
+        assert!((after - before).0.length() < 1e-4);
+
+        // The bodies now move apart along the line of centers.
+        assert!((projectile_v - target_v).0.dot(impact.normal) > 0.0);
+    }
+}
@@ -0,0 +1,182 @@
+use std::f32::consts::TAU;
+
+use bevy::{prelude::*, render::view::NoFrustumCulling};
+use rand::prelude::*;
+
+use crate::components::{
+    from_vec3, to_f32, to_vec3, Acceleration, Mass, Position, PreviousPosition, Radius, RealVec3,
+    SubstepStart, Time, Velocity,
+};
+
+use self::instancing::{InstanceData, InstancingPlugin, ParticleInstances};
+
+use super::{
+    disk::Star,
+    gravity,
+    simulation::{interpolation_alpha, PhysicsSet, SimClock, SimRng, SimulationStep},
+    stars::{circular_velocity, StarSystemPreset},
+    Constants, Planet,
+};
+
+mod instancing;
+
+/// A massless body, for dust and planetesimals. It feels the gravity of every
+/// [`Planet`] but pulls on nothing, so particles are cheap enough to have by
+/// the hundred thousand.
+#[derive(Component)]
+pub struct TestParticle;
+
+#[derive(Event, Clone, Copy)]
+pub struct SpawnTestParticlesEvent {
+    pub count: usize,
+}
+
+pub struct TestParticlePlugin;
+
+impl Plugin for TestParticlePlugin {
+    fn build(&self, app: &mut App) {
+        app // <no autoformat>
+            .add_plugins(InstancingPlugin)
+            .add_event::<SpawnTestParticlesEvent>()
+            .add_systems(Startup, spawn_particle_cloud)
+            .add_systems(
+                SimulationStep,
+                test_particle_system
+                    .after(PhysicsSet::Integrate)
+                    .before(PhysicsSet::Collisions),
+            )
+            .add_systems(
+                PostUpdate,
+                (spawn_test_particles_system, update_instances_system),
+            );
+    }
+}
+
+/// Rendered size of a test particle. They have no physical size.
+const PARTICLE_SCALE: f32 = 0.6;
+const PARTICLE_COLOR: Color = Color::rgb(0.8, 0.7, 0.55);
+
+/// Spawns the entity every test particle is drawn through.
+fn spawn_particle_cloud(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>) {
+    commands.spawn((
+        Name::new("Test Particles"),
+        meshes.add(
+            shape::UVSphere {
+                radius: 1.0,
+                sectors: 6,
+                stacks: 4,
+            }
+            .into(),
+        ),
+        SpatialBundle::INHERITED_IDENTITY,
+        ParticleInstances::default(),
+        // The instances are scattered all over, so the mesh's own bounding box
+        // says nothing about whether they're on screen.
+        NoFrustumCulling,
+    ));
+}
+
+fn spawn_test_particles_system(
+    mut ereader: EventReader<SpawnTestParticlesEvent>,
+    mut commands: Commands,
+    constants: Res<Constants>,
+    preset: Res<StarSystemPreset>,
+    stars: Query<(&Position, &Velocity, &Mass), With<Star>>,
+    mut rng: ResMut<SimRng>,
+) {
+    let rng = &mut rng.0;
+
+    for event in ereader.iter() {
+        let particles = (0..event.count)
+            .map(|_| {
+                let pos = from_vec3(
+                    to_f32(rng.gen_range(preset.planet_orbits()))
+                        * (Quat::from_axis_angle(Vec3::Y, rng.gen_range(0.0..TAU))
+                            .mul_vec3(Vec3::X)
+                            + rng.gen_range(-0.05..0.05) * Vec3::Y),
+                );
+                let vel = circular_velocity(&constants, &stars, pos);
+
+                (TestParticle, Position(pos), PreviousPosition(pos), vel)
+            })
+            .collect::<Vec<_>>();
+
+        commands.spawn_batch(particles);
+    }
+}
+
+/// Gravitational acceleration of a test particle at `pos` toward each of
+/// `sources`.
+fn acceleration(
+    constants: &Constants,
+    pos: RealVec3,
+    sources: impl Iterator<Item = (RealVec3, Mass)>,
+) -> Acceleration {
+    Acceleration(
+        sources
+            .map(|(source, mass)| gravity::attraction(constants, 1.0, mass.0, source - pos))
+            .sum(),
+    )
+}
+
+type TestParticlePlanetsData<'a, 'b, 'c, 'd> =
+    (&'a SubstepStart, &'b Position, &'c Mass, &'d Radius);
+
+/// Advances every test particle by one substep with a kick-drift-kick
+/// leapfrog. The first kick uses where the planets were at the start of the
+/// substep and the second where they ended up, so this runs after the planets
+/// have been integrated.
+fn test_particle_system(
+    mut particles: Query<(Entity, &mut Position, &mut Velocity), With<TestParticle>>,
+    planets: Query<TestParticlePlanetsData, (With<Planet>, Without<TestParticle>)>,
+    constants: Res<Constants>,
+    clock: Res<SimClock>,
+    par_commands: ParallelCommands,
+) {
+    let planets = planets
+        .iter()
+        .map(|(start, pos, &mass, &radius)| (start.0, pos.0, mass, radius))
+        .collect::<Vec<_>>();
+    let constants = &*constants;
+    let half_dt = Time(0.5 * clock.dt.0);
+
+    particles
+        .par_iter_mut()
+        .for_each_mut(|(entity, mut pos, mut vel)| {
+            let before = planets.iter().map(|&(start, _, mass, _)| (start, mass));
+            *vel += acceleration(constants, pos.0, before) * half_dt;
+
+            pos.0 += *vel * clock.dt;
+
+            let after = planets.iter().map(|&(_, end, mass, _)| (end, mass));
+            *vel += acceleration(constants, pos.0, after) * half_dt;
+
+            if constants.accrete_test_particles
+                && planets
+                    .iter()
+                    .any(|&(_, end, _, radius)| end.distance_squared(pos.0) < radius.0 * radius.0)
+            {
+                par_commands.command_scope(|mut commands| {
+                    commands.entity(entity).despawn();
+                });
+            }
+        });
+}
+
+fn update_instances_system(
+    particles: Query<(&Position, &PreviousPosition), With<TestParticle>>,
+    mut clouds: Query<&mut ParticleInstances>,
+    fixed_time: Res<FixedTime>,
+) {
+    let alpha = interpolation_alpha(&fixed_time);
+    let color = PARTICLE_COLOR.as_rgba_f32();
+
+    for mut instances in &mut clouds {
+        instances.clear();
+        instances.extend(particles.iter().map(|(pos, prev)| InstanceData {
+            position: to_vec3(prev.0.lerp(pos.0, alpha)),
+            scale: PARTICLE_SCALE,
+            color,
+        }));
+    }
+}
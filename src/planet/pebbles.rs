@@ -0,0 +1,253 @@
+//! Growth by sweeping up the pebbles drifting in through the gas, after
+//! Lambrechts & Johansen (2014).
+
+use bevy::prelude::*;
+
+use crate::components::{consts, Mass, Position, Real, Time, Velocity};
+
+use super::{
+    accrete,
+    composition::Composition,
+    diagnostics::Conservation,
+    disk::{self, Star},
+    gas::GasDisk,
+    simulation::{PhysicsSet, SimClock, SimulationStep},
+    stars::orbit_center,
+    AccretingPlanetsData, Constants, Planet,
+};
+
+pub struct PebbleAccretionPlugin;
+
+impl Plugin for PebbleAccretionPlugin {
+    fn build(&self, app: &mut App) {
+        app // <no autoformat>
+            .add_systems(
+                SimulationStep,
+                pebble_accretion_system.in_set(PhysicsSet::Accretion),
+            );
+    }
+}
+
+/// Aspect ratio at which the pebble isolation mass is
+/// [`Constants::pebble_isolation_mass`].
+const ISOLATION_ASPECT_RATIO: Real = 0.05;
+
+/// Mass past which a planet stirs up the gas enough to stop pebbles reaching
+/// it, where the disk's aspect ratio is `aspect_ratio`.
+pub fn isolation_mass(constants: &Constants, aspect_ratio: Real) -> Mass {
+    Mass(constants.pebble_isolation_mass * (aspect_ratio / ISOLATION_ASPECT_RATIO).powi(3))
+}
+
+/// Rate at which a planet of mass `mass`, `r` from a star of mass
+/// `star_mass`, sweeps up pebbles out of a flux of `flux` drifting past it.
+/// This is the Hill regime rate, capped at the whole flux.
+fn accretion_rate(
+    constants: &Constants,
+    gas: &GasDisk,
+    star_mass: Mass,
+    mass: Mass,
+    r: Real,
+    flux: Real,
+) -> Real {
+    let stokes = constants.pebble_stokes_number;
+    let omega = (constants.grav_const * star_mass.0 / r.powi(3)).sqrt();
+    let hill_radius = r * (mass.0 / (3.0 * star_mass.0)).cbrt();
+
+    // Pebbles drift in because the gas around them orbits slower than they
+    // would, and the flux is spread over a ring moving at that speed.
+    let drift_speed =
+        2.0 * stokes * gas.sub_keplerian_fraction(r) * omega * r / (1.0 + stokes * stokes);
+    let surface_density = flux / (2.0 * consts::PI * r * drift_speed);
+
+    let rate = 2.0 * (stokes / 0.1).powf(2.0 / 3.0) * omega * hill_radius.powi(2) * surface_density;
+    rate.min(flux)
+}
+
+/// How much each of `planets`, given as `(r, star_mass, mass)` from the
+/// outside in, sweeps up out of `flux` over `dt`. Each one only gets what the
+/// planets beyond it let through, and none grows past its isolation mass.
+fn pebble_growth(
+    constants: &Constants,
+    gas: &GasDisk,
+    mut flux: Real,
+    dt: Time,
+    planets: impl IntoIterator<Item = (Real, Mass, Mass)>,
+) -> Vec<Mass> {
+    planets
+        .into_iter()
+        .map(|(r, star_mass, mass)| {
+            let isolation = isolation_mass(constants, gas.aspect_ratio_at(r));
+            if flux <= 0.0 || r == 0.0 || mass.0 >= isolation.0 {
+                return Mass::ZERO;
+            }
+
+            let rate = accretion_rate(constants, gas, star_mass, mass, r, flux);
+            flux -= rate;
+
+            Mass((rate * dt.0).min(isolation.0 - mass.0))
+        })
+        .collect()
+}
+
+/// Feeds the pebble flux to the planets from the outside in.
+pub fn pebble_accretion_system(
+    mut planets: Query<AccretingPlanetsData, (With<Planet>, Without<Star>)>,
+    stars: Query<(Entity, &Star, &Position, &Velocity, &Mass)>,
+    mut conservation: ResMut<Conservation>,
+    gas: Res<GasDisk>,
+    constants: Res<Constants>,
+    clock: Res<SimClock>,
+) {
+    if !constants.pebble_accretion || !gas.enabled {
+        return;
+    }
+
+    let flux = constants.pebble_flux * gas.remaining(clock.elapsed);
+
+    let bodies = stars
+        .iter()
+        .map(|(e, _, pos, _, &mass)| (e, pos.0, mass))
+        .chain(
+            planets
+                .iter()
+                .map(|(e, pos, _, &mass, ..)| (e, pos.0, mass)),
+        )
+        .collect::<Vec<_>>();
+
+    let centers = || stars.iter().map(|(_, _, pos, vel, mass)| (pos, vel, mass));
+    let mut planets = planets
+        .iter_mut()
+        .filter_map(|planet| {
+            let (_, pos, ..) = &planet;
+            let center = orbit_center(centers(), pos.0)?;
+            Some((pos.0.distance(center.pos), center.mass, planet))
+        })
+        .collect::<Vec<_>>();
+    planets.sort_by(|(a, ..), (b, ..)| b.total_cmp(a));
+
+    let growth = pebble_growth(
+        &constants,
+        &gas,
+        flux,
+        clock.dt,
+        planets
+            .iter()
+            .map(|(r, star_mass, (_, _, _, mass, ..))| (*r, *star_mass, **mass)),
+    );
+
+    for ((_, _, (e, pos, vel, mut mass, mut radius, mut composition, mut spin)), accreted) in
+        planets.into_iter().zip(growth)
+    {
+        if accreted.0 <= 0.0 {
+            continue;
+        }
+
+        let pebbles = Composition::solids_at(disk::temperature(
+            stars.iter().map(|(_, star, pos, ..)| (star, pos)),
+            pos.0,
+        ));
+        accrete(
+            &constants,
+            &mut conservation,
+            &bodies,
+            (
+                e,
+                pos,
+                vel,
+                &mut mass,
+                &mut radius,
+                &mut composition,
+                &mut spin,
+            ),
+            accreted,
+            pebbles,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::planet::disk::AU;
+
+    const STAR_MASS: Mass = Mass(1000.0);
+
+    fn setup() -> (Constants, GasDisk) {
+        (Constants::default(), GasDisk::default())
+    }
+
+    #[test]
+    fn growth_stops_at_isolation_mass() {
+        let (constants, gas) = setup();
+        let isolation = isolation_mass(&constants, gas.aspect_ratio_at(AU));
+
+        // Far more flux and time than it takes to reach isolation.
+        let growth = pebble_growth(
+            &constants,
+            &gas,
+            1e6,
+            Time(1e6),
+            [
+                (AU, STAR_MASS, Mass(0.9 * isolation.0)),
+                (AU, STAR_MASS, isolation),
+            ],
+        );
+
+        assert!((growth[0].0 - 0.1 * isolation.0).abs() < 1e-4 * isolation.0);
+        assert_eq!(growth[1], Mass::ZERO);
+    }
+
+    #[test]
+    fn outer_planets_take_flux_first() {
+        let (constants, gas) = setup();
+        let flux = constants.pebble_flux;
+        let dt = Time(1.0);
+        let (outer, inner) = ((2.0 * AU, Mass(0.05)), (AU, Mass(0.05)));
+
+        let growth = pebble_growth(
+            &constants,
+            &gas,
+            flux,
+            dt,
+            [(outer.0, STAR_MASS, outer.1), (inner.0, STAR_MASS, inner.1)],
+        );
+
+        let outer_rate = accretion_rate(&constants, &gas, STAR_MASS, outer.1, outer.0, flux);
+        assert!(outer_rate > 0.0 && outer_rate < flux);
+        assert!((growth[0].0 / (outer_rate * dt.0) - 1.0).abs() < 1e-4);
+        assert!(growth[1].0 <= (flux - outer_rate) * dt.0);
+
+        let inner_rate = accretion_rate(
+            &constants,
+            &gas,
+            STAR_MASS,
+            inner.1,
+            inner.0,
+            flux - outer_rate,
+        );
+        assert!(inner_rate > 0.0);
+        assert!((growth[1].0 / (inner_rate * dt.0) - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn nothing_gets_past_a_planet_taking_the_whole_flux() {
+        let (constants, gas) = setup();
+
+        // Big enough that its Hill rate exceeds the flux, but still below its
+        // isolation mass.
+        let isolation = isolation_mass(&constants, gas.aspect_ratio_at(2.0 * AU));
+        let growth = pebble_growth(
+            &constants,
+            &gas,
+            1e-6,
+            Time(1.0),
+            [
+                (2.0 * AU, STAR_MASS, Mass(0.99 * isolation.0)),
+                (AU, STAR_MASS, Mass(5.0)),
+            ],
+        );
+
+        assert!(growth[0].0 > 0.0);
+        assert_eq!(growth[1], Mass::ZERO);
+    }
+}
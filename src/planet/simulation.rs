@@ -0,0 +1,160 @@
+use std::time::Duration;
+
+use bevy::{ecs::schedule::ScheduleLabel, prelude::*, transform::TransformSystem};
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::components::{self, from_f32, to_vec3, Position, PreviousPosition, Real, SubstepStart};
+
+/// Runs the n-body pipeline on a fixed timestep and interpolates the rendered
+/// `Transform`s between the last two physics states.
+pub struct SimulationPlugin;
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        let settings = *app.world.get_resource_or_insert_with(SimSettings::default);
+
+        app // <no autoformat>
+            .insert_resource(FixedTime::new_from_secs(settings.step_size))
+            .insert_resource(SimRng(StdRng::seed_from_u64(settings.seed)))
+            .init_resource::<SimClock>()
+            .init_schedule(SimulationStep)
+            .configure_sets(
+                SimulationStep,
+                (
+                    PhysicsSet::Forces,
+                    PhysicsSet::Integrate,
+                    PhysicsSet::Accretion,
+                    PhysicsSet::Collisions,
+                    PhysicsSet::Diagnostics,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                SimulationStep,
+                store_substep_start_system.before(PhysicsSet::Forces),
+            )
+            .add_systems(Update, sync_fixed_time_system)
+            .add_systems(
+                FixedUpdate,
+                (store_previous_positions_system, run_substeps_system).chain(),
+            )
+            .add_systems(
+                PostUpdate,
+                interpolate_transforms_system.before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+/// Schedule holding everything that advances the simulation by one substep.
+/// It is run `SimSettings::substeps` times per fixed tick.
+#[derive(ScheduleLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SimulationStep;
+
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PhysicsSet {
+    /// Systems which accumulate into each body's `Force`.
+    Forces,
+    /// Advances positions and velocities by one substep.
+    Integrate,
+    /// Grows bodies out of material that isn't simulated body by body.
+    Accretion,
+    /// Merges bodies which were found to be overlapping.
+    Collisions,
+    /// Measures the state the step left behind.
+    Diagnostics,
+}
+
+#[derive(Resource, Clone, Copy)]
+pub struct SimSettings {
+    /// Wall-clock seconds between fixed ticks. This is also the amount of
+    /// simulated time that passes per tick.
+    pub step_size: f32,
+    /// How many integration steps each fixed tick is divided into.
+    pub substeps: u32,
+    /// Seed for [`SimRng`]. Two runs with the same seed and the same inputs
+    /// produce the same trajectories.
+    pub seed: u64,
+}
+
+impl Default for SimSettings {
+    fn default() -> Self {
+        Self {
+            step_size: 1.0 / 60.0,
+            substeps: 4,
+            seed: 0,
+        }
+    }
+}
+
+impl SimSettings {
+    pub fn substep_dt(&self) -> components::Time {
+        components::Time(from_f32(self.step_size) / self.substeps.max(1) as Real)
+    }
+}
+
+/// Simulated time, independent of the wall clock.
+#[derive(Resource, Default, Debug, Clone, Copy)]
+pub struct SimClock {
+    /// Total simulated time so far.
+    pub elapsed: components::Time,
+    /// Number of substeps taken so far.
+    pub steps: u64,
+    /// Length of the substep currently being taken.
+    pub dt: components::Time,
+}
+
+/// The random number generator used by the simulation. Draw from this instead
+/// of `thread_rng` so that runs can be reproduced.
+#[derive(Resource)]
+pub struct SimRng(pub StdRng);
+
+fn sync_fixed_time_system(settings: Res<SimSettings>, mut fixed_time: ResMut<FixedTime>) {
+    if settings.is_changed() {
+        fixed_time.period = Duration::from_secs_f32(settings.step_size);
+    }
+}
+
+fn store_previous_positions_system(mut query: Query<(&Position, &mut PreviousPosition)>) {
+    for (pos, mut prev) in &mut query {
+        prev.0 = pos.0;
+    }
+}
+
+fn store_substep_start_system(mut query: Query<(&Position, &mut SubstepStart)>) {
+    for (pos, mut start) in &mut query {
+        start.0 = pos.0;
+    }
+}
+
+fn run_substeps_system(world: &mut World) {
+    let settings = *world.resource::<SimSettings>();
+    let dt = settings.substep_dt();
+
+    for _ in 0..settings.substeps.max(1) {
+        world.resource_mut::<SimClock>().dt = dt;
+        world.run_schedule(SimulationStep);
+
+        let mut clock = world.resource_mut::<SimClock>();
+        clock.elapsed += dt;
+        clock.steps += 1;
+    }
+}
+
+/// How far the wall clock has moved from the last fixed tick toward the next,
+/// from 0 to 1. Rendered positions are interpolated by this much between
+/// [`PreviousPosition`] and [`Position`].
+pub fn interpolation_alpha(fixed_time: &FixedTime) -> Real {
+    let alpha = fixed_time.accumulated().as_secs_f32() / fixed_time.period.as_secs_f32();
+    from_f32(alpha.clamp(0.0, 1.0))
+}
+
+fn interpolate_transforms_system(
+    mut query: Query<(&mut Transform, &Position, &PreviousPosition)>,
+    fixed_time: Res<FixedTime>,
+) {
+    let alpha = interpolation_alpha(&fixed_time);
+
+    for (mut tsf, pos, prev) in &mut query {
+        tsf.translation = to_vec3(prev.0.lerp(pos.0, alpha));
+    }
+}
@@ -0,0 +1,492 @@
+//! The stars at the heart of the system: a single star or a binary, and which
+//! of them a body orbits.
+
+use std::ops::Range;
+
+use bevy::{ecs::system::EntityCommands, prelude::*};
+
+use crate::components::{
+    to_f32, to_vec3, Force, Mass, Position, PreviousPosition, Radius, Real, RealVec3, Spin,
+    SubstepStart, Velocity,
+};
+
+use super::{
+    composition::{Composition, Densities},
+    disk::{Star, AU},
+    orbit::OrbitalElements,
+    planet_mesh, radius_from_mass, Constants, Planet,
+};
+
+pub struct StarsPlugin;
+
+impl Plugin for StarsPlugin {
+    fn build(&self, app: &mut App) {
+        app // <no autoformat>
+            .register_type::<StellarAccretion>()
+            .init_resource::<StarSystemPreset>()
+            .add_event::<SpawnStarSystemEvent>()
+            .add_systems(Startup, spawn_initial_stars)
+            .add_systems(Update, star_appearance_system)
+            .add_systems(PostUpdate, spawn_star_system_system);
+    }
+}
+
+/// Point light intensity of a star of one solar luminosity.
+const SOLAR_LIGHT_INTENSITY: f32 = 50_000_000.0;
+
+/// Exponent of the main-sequence mass–luminosity relation, L ∝ M^3.5.
+const MASS_LUMINOSITY_EXPONENT: Real = 3.5;
+
+/// A ready-made arrangement of stars. The current one is kept as a resource.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StarSystemPreset {
+    #[default]
+    Single,
+    /// A wide binary, with the planets orbiting the primary alone.
+    SType,
+    /// A close binary, with the planets orbiting both stars.
+    PType,
+}
+
+impl StarSystemPreset {
+    pub const ALL: [Self; 3] = [Self::Single, Self::SType, Self::PType];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Single => "Single Star",
+            Self::SType => "S-type Binary",
+            Self::PType => "P-type Binary",
+        }
+    }
+
+    /// Distances from the origin at which planets are spawned by default.
+    /// Around a close binary they have to keep clear of the region where the
+    /// stars' orbit makes theirs unstable, a few times the binary's separation
+    /// out (Holman & Wiegert 1999).
+    pub fn planet_orbits(&self) -> Range<Real> {
+        match self {
+            Self::Single | Self::SType => 50.0..500.0,
+            Self::PType => 2.5 * AU..6.0 * AU,
+        }
+    }
+
+    fn stars(&self, grav_const: Real) -> Vec<StarSpec> {
+        let sun = StarSpec {
+            name: "Sun",
+            mass: Mass(1000.0),
+            star: Star::default(),
+            pos: RealVec3::ZERO,
+            vel: Velocity::ZERO,
+        };
+
+        match self {
+            Self::Single => vec![sun],
+            Self::SType => {
+                let companion = StarSpec {
+                    name: "Companion",
+                    mass: Mass(300.0),
+                    star: Star {
+                        luminosity: 0.05,
+                        color: Color::rgb(1.0, 0.45, 0.3),
+                    },
+                    ..sun
+                };
+
+                // Keep the primary, and so the planets' orbits, at the origin.
+                let mut stars = binary(grav_const, sun, companion, 20.0 * AU);
+                let offset = stars[0].pos;
+                for star in &mut stars {
+                    star.pos -= offset;
+                }
+                stars.into()
+            }
+            Self::PType => {
+                let a = StarSpec {
+                    name: "Star A",
+                    mass: Mass(600.0),
+                    star: Star {
+                        luminosity: 0.5,
+                        ..default()
+                    },
+                    ..sun
+                };
+                let b = StarSpec {
+                    name: "Star B",
+                    mass: Mass(400.0),
+                    star: Star {
+                        luminosity: 0.2,
+                        color: Color::rgb(1.0, 0.6, 0.35),
+                    },
+                    ..sun
+                };
+
+                binary(grav_const, a, b, AU).into()
+            }
+        }
+    }
+}
+
+/// Everything needed to spawn a star.
+#[derive(Clone, Copy)]
+struct StarSpec {
+    name: &'static str,
+    mass: Mass,
+    star: Star,
+    pos: RealVec3,
+    vel: Velocity,
+}
+
+/// Puts two stars on a circular orbit `separation` apart in the ecliptic,
+/// about their barycenter at the origin.
+fn binary(grav_const: Real, a: StarSpec, b: StarSpec, separation: Real) -> [StarSpec; 2] {
+    let total = a.mass.0 + b.mass.0;
+    let speed = (grav_const * total / separation).sqrt();
+
+    // Each star is as far from the barycenter, and as fast, as the other's
+    // share of the mass. They go around the same way as the planets.
+    let place = |spec: StarSpec, share: Real, dir: RealVec3| StarSpec {
+        pos: share * separation * dir,
+        vel: Velocity(-share * speed * dir.cross(RealVec3::Y)),
+        ..spec
+    };
+
+    [
+        place(a, b.mass.0 / total, RealVec3::X),
+        place(b, a.mass.0 / total, -RealVec3::X),
+    ]
+}
+
+/// Replaces every star with those of `preset`. Planets are left where they
+/// are.
+#[derive(Event, Clone, Copy)]
+pub struct SpawnStarSystemEvent {
+    pub preset: StarSystemPreset,
+}
+
+fn spawn_initial_stars(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    constants: Res<Constants>,
+    preset: Res<StarSystemPreset>,
+) {
+    for spec in preset.stars(constants.grav_const) {
+        spawn_star(
+            &mut commands,
+            &mut meshes,
+            &mut materials,
+            &constants.densities,
+            spec,
+        );
+    }
+}
+
+fn spawn_star_system_system(
+    mut ereader: EventReader<SpawnStarSystemEvent>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    constants: Res<Constants>,
+    mut preset: ResMut<StarSystemPreset>,
+    stars: Query<Entity, With<Star>>,
+) {
+    let Some(event) = ereader.iter().last() else {
+        return;
+    };
+
+    for entity in &stars {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    *preset = event.preset;
+    for spec in preset.stars(constants.grav_const) {
+        spawn_star(
+            &mut commands,
+            &mut meshes,
+            &mut materials,
+            &constants.densities,
+            spec,
+        );
+    }
+}
+
+/// Material the sphere of a star is drawn with.
+fn star_material(star: &Star) -> StandardMaterial {
+    StandardMaterial {
+        base_color: star.color,
+        emissive: star.color * 1.5,
+        ..default()
+    }
+}
+
+fn star_light(star: &Star) -> PointLight {
+    PointLight {
+        intensity: SOLAR_LIGHT_INTENSITY * to_f32(star.luminosity),
+        range: 10_000.0,
+        radius: 3.0,
+        color: star.color,
+        shadows_enabled: true,
+        ..default()
+    }
+}
+
+fn spawn_star<'w, 's, 'a>(
+    commands: &'a mut Commands<'w, 's>,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    densities: &Densities,
+    spec: StarSpec,
+) -> EntityCommands<'w, 's, 'a> {
+    let composition = Composition::GAS;
+    let radius = radius_from_mass(spec.mass, composition.density(densities));
+
+    let mut star = commands.spawn((
+        PointLightBundle {
+            transform: Transform::from_translation(to_vec3(spec.pos)),
+            point_light: star_light(&spec.star),
+            ..default()
+        },
+        spec.star,
+        StellarAccretion::default(),
+        OrbitalElements::default(),
+        Planet,
+        Name::new(spec.name),
+        radius,
+        spec.mass,
+        composition,
+        Position(spec.pos),
+        PreviousPosition(spec.pos),
+        SubstepStart(spec.pos),
+        spec.vel,
+        Force::ZERO,
+        Spin::at_rest(spec.mass, radius),
+    ));
+
+    star.with_children(|builder| {
+        builder.spawn(PbrBundle {
+            mesh: meshes.add(planet_mesh(radius)),
+            material: materials.add(star_material(&spec.star)),
+            ..default()
+        });
+    });
+
+    star
+}
+
+/// Everything a star has swallowed since it was spawned.
+#[derive(Component, Reflect, Default, Debug, Clone, Copy)]
+pub struct StellarAccretion {
+    pub mass: Mass,
+    pub bodies: usize,
+}
+
+/// Grows a star from `before` to `after` by swallowing `bodies` bodies, among
+/// them the `swallowed_stars` with their masses. The light of those stars is
+/// added to its own, and it brightens along the main sequence with the rest of
+/// the mass it takes on.
+pub fn accrete_onto_star(
+    star: &mut Star,
+    accretion: &mut StellarAccretion,
+    before: Mass,
+    after: Mass,
+    swallowed_stars: &[(Mass, Star)],
+    bodies: usize,
+) {
+    let stellar_mass = before + swallowed_stars.iter().map(|&(mass, _)| mass).sum::<Mass>();
+    star.luminosity += swallowed_stars
+        .iter()
+        .map(|(_, other)| other.luminosity)
+        .sum::<Real>();
+    star.luminosity *= (after.0 / stellar_mass.0).powf(MASS_LUMINOSITY_EXPONENT);
+    accretion.mass += after - before;
+    accretion.bodies += bodies;
+}
+
+/// Keeps each star's light, glow and size in step with its luminosity, color
+/// and radius.
+fn star_appearance_system(
+    mut stars: Query<(Ref<Star>, Ref<Radius>, &mut PointLight, &Children)>,
+    handles: Query<(&Handle<Mesh>, &Handle<StandardMaterial>)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for (star, radius, mut light, children) in &mut stars {
+        let star_changed = star.is_changed() && !star.is_added();
+        let radius_changed = radius.is_changed() && !radius.is_added();
+
+        if star_changed {
+            *light = star_light(&star);
+        }
+
+        for (mesh, material) in handles.iter_many(children.iter()) {
+            if radius_changed {
+                if let Some(mesh) = meshes.get_mut(mesh) {
+                    *mesh = planet_mesh(*radius);
+                }
+            }
+
+            if star_changed {
+                if let Some(material) = materials.get_mut(material) {
+                    *material = star_material(&star);
+                }
+            }
+        }
+    }
+}
+
+/// What a body orbits: a single star or the barycenter of several.
+#[derive(Debug, Clone, Copy)]
+pub struct OrbitCenter {
+    pub pos: RealVec3,
+    pub vel: Velocity,
+    pub mass: Mass,
+}
+
+/// What a body at `pos` orbits. That's the star pulling hardest on it if it's
+/// within that star's Hill sphere against the other stars, and otherwise the
+/// barycenter of all of them. `None` if there are no stars.
+pub fn orbit_center<'a>(
+    stars: impl IntoIterator<Item = (&'a Position, &'a Velocity, &'a Mass)>,
+    pos: RealVec3,
+) -> Option<OrbitCenter> {
+    let stars = stars
+        .into_iter()
+        .map(|(star_pos, &vel, &mass)| OrbitCenter {
+            pos: star_pos.0,
+            vel,
+            mass,
+        })
+        .collect::<Vec<_>>();
+
+    let total = stars.iter().map(|star| star.mass.0).sum::<Real>();
+    let moment = stars
+        .iter()
+        .map(|star| star.mass.0 * star.pos)
+        .sum::<RealVec3>();
+    let momentum = stars
+        .iter()
+        .map(|star| star.mass.0 * star.vel.0)
+        .sum::<RealVec3>();
+    let barycenter = OrbitCenter {
+        pos: moment / total,
+        vel: Velocity(momentum / total),
+        mass: Mass(total),
+    };
+
+    let nearest = stars.iter().copied().max_by(|a, b| {
+        let pull = |star: &OrbitCenter| star.mass.0 / star.pos.distance_squared(pos);
+        pull(a).total_cmp(&pull(b))
+    })?;
+
+    let rest = total - nearest.mass.0;
+    if rest <= 0.0 {
+        return Some(nearest);
+    }
+
+    let rest_pos = (moment - nearest.mass.0 * nearest.pos) / rest;
+    let hill_radius = nearest.pos.distance(rest_pos) * (nearest.mass.0 / (3.0 * rest)).cbrt();
+
+    if nearest.pos.distance(pos) < hill_radius {
+        Some(nearest)
+    } else {
+        Some(barycenter)
+    }
+}
+
+/// Velocity of a circular orbit at `pos` around whatever it orbits, or rest
+/// if there are no stars.
+pub fn circular_velocity<'a>(
+    constants: &Constants,
+    stars: impl IntoIterator<Item = (&'a Position, &'a Velocity, &'a Mass)>,
+    pos: RealVec3,
+) -> Velocity {
+    let Some(center) = orbit_center(stars, pos) else {
+        return Velocity::ZERO;
+    };
+
+    let offset = pos - center.pos;
+    let orbit_speed = Real::sqrt(constants.grav_const * center.mass.0 * offset.length_recip());
+    center.vel + Velocity(-orbit_speed * offset.normalize().cross(RealVec3::Y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bodies(stars: &[StarSpec]) -> Vec<(Position, Velocity, Mass)> {
+        stars
+            .iter()
+            .map(|star| (Position(star.pos), star.vel, star.mass))
+            .collect()
+    }
+
+    fn iter(
+        bodies: &[(Position, Velocity, Mass)],
+    ) -> impl Iterator<Item = (&Position, &Velocity, &Mass)> {
+        bodies.iter().map(|(pos, vel, mass)| (pos, vel, mass))
+    }
+
+    #[test]
+    fn binaries_have_no_net_momentum() {
+        for preset in StarSystemPreset::ALL {
+            let momentum = preset
+                .stars(20.0)
+                .iter()
+                .map(|star| star.mass.0 * star.vel.0)
+                .sum::<RealVec3>();
+            assert!(momentum.length() < 1e-3, "{}", preset.name());
+        }
+    }
+
+    #[test]
+    fn swallowing_brightens_star() {
+        let mut star = Star::default();
+        let mut accretion = StellarAccretion::default();
+        accrete_onto_star(
+            &mut star,
+            &mut accretion,
+            Mass(1000.0),
+            Mass(1100.0),
+            &[],
+            2,
+        );
+
+        assert!((star.luminosity - Real::powf(1.1, 3.5)).abs() < 1e-4);
+        assert!((accretion.mass.0 - 100.0).abs() < 1e-4);
+        assert_eq!(accretion.bodies, 2);
+    }
+
+    #[test]
+    fn merging_stars_add_their_light() {
+        let mut star = Star::default();
+        let mut accretion = StellarAccretion::default();
+        let other = Star {
+            luminosity: 0.5,
+            ..default()
+        };
+        accrete_onto_star(
+            &mut star,
+            &mut accretion,
+            Mass(1000.0),
+            Mass(1500.0),
+            &[(Mass(500.0), other)],
+            1,
+        );
+
+        // Nothing but the two stars merged, so their light simply adds.
+        assert!((star.luminosity - 1.5).abs() < 1e-4);
+        assert!((accretion.mass.0 - 500.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn planets_orbit_the_right_center() {
+        let s_type = bodies(&StarSystemPreset::SType.stars(20.0));
+        let center = orbit_center(iter(&s_type), RealVec3::new(300.0, 0.0, 0.0)).unwrap();
+        assert_eq!(center.mass.0, 1000.0);
+
+        let p_type = bodies(&StarSystemPreset::PType.stars(20.0));
+        let center = orbit_center(iter(&p_type), RealVec3::new(400.0, 0.0, 0.0)).unwrap();
+        assert_eq!(center.mass.0, 1000.0);
+        assert!(center.pos.length() < 1e-3);
+    }
+}
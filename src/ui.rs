@@ -1,3 +1,5 @@
+use std::ops::RangeInclusive;
+
 use bevy::{prelude::*, window::PrimaryWindow};
 use bevy_inspector_egui::{
     bevy_egui::{egui, EguiContexts, EguiPlugin},
@@ -7,13 +9,39 @@ use bevy_inspector_egui::{
 use rand::Rng;
 
 use crate::{
-    planet::{Constants, SpawnPlanetEvent},
+    components::{from_vec3, Real},
+    planet::{
+        adaptive::AdaptiveStep,
+        composition::{Densities, Material},
+        gravity::{GravitySolver, Softening},
+        integrator::IntegratorKind,
+        octree::OctreeOverlay,
+        outcomes::CollisionModel,
+        particles::{SpawnTestParticlesEvent, TestParticle},
+        simulation::{SimClock, SimSettings},
+        stars::StarSystemPreset,
+        Constants, SpawnPlanetEvent,
+    },
     MainCamera,
 };
 
-use self::planet_spawning::{PlanetSpawnMode, PlanetSpawningPlugin};
+use self::{
+    conservation::ConservationUiPlugin,
+    disk_generator::DiskGeneratorUiPlugin,
+    gas::GasUiPlugin,
+    orbit_spawning::OrbitSpawningPlugin,
+    planet_spawning::{PlanetSpawnMode, PlanetSpawningPlugin},
+    selection::SelectionPlugin,
+    stars::StarsUiPlugin,
+};
 
+mod conservation;
+mod disk_generator;
+mod gas;
+mod orbit_spawning;
 mod planet_spawning;
+mod selection;
+mod stars;
 
 pub struct MyUiPlugin;
 
@@ -21,7 +49,13 @@ pub struct MyUiPlugin;
 pub struct UiState {
     right_panel_open: bool,
     world_inspector_open: bool,
+    conservation_open: bool,
+    stars_open: bool,
+    gas_open: bool,
+    orbit_form_open: bool,
+    disk_generator_open: bool,
     new_planet_pos: Vec3,
+    star_system_preset: StarSystemPreset,
 }
 
 #[allow(clippy::derivable_impls)]
@@ -30,7 +64,13 @@ impl Default for UiState {
         Self {
             right_panel_open: false,
             world_inspector_open: false,
+            conservation_open: false,
+            stars_open: false,
+            gas_open: false,
+            orbit_form_open: false,
+            disk_generator_open: false,
             new_planet_pos: Vec3::ZERO,
+            star_system_preset: StarSystemPreset::default(),
         }
     }
 }
@@ -44,6 +84,12 @@ impl Plugin for MyUiPlugin {
                 EguiPlugin,
                 WorldInspectorPlugin::new().run_if(world_inspector_open),
                 PlanetSpawningPlugin,
+                ConservationUiPlugin,
+                StarsUiPlugin,
+                GasUiPlugin,
+                OrbitSpawningPlugin,
+                DiskGeneratorUiPlugin,
+                SelectionPlugin,
             ))
             .insert_resource(UiState::default())
             .insert_resource(MouseRay::default())
@@ -56,6 +102,7 @@ fn world_inspector_open(state: Res<UiState>) -> bool {
     state.world_inspector_open
 }
 
+#[allow(clippy::too_many_arguments)]
 fn root_ui_system(
     mut contexts: EguiContexts,
     mut state: ResMut<UiState>,
@@ -63,11 +110,37 @@ fn root_ui_system(
     mut constants: ResMut<Constants>,
     mut spawn_events: EventWriter<SpawnPlanetEvent>,
     mut planet_spawn_mode: ResMut<PlanetSpawnMode>,
+    mut sim_settings: ResMut<SimSettings>,
+    sim_clock: Res<SimClock>,
+    mut octree_overlay: ResMut<OctreeOverlay>,
+    mut adaptive: ResMut<AdaptiveStep>,
+    mut spawn_particle_events: EventWriter<SpawnTestParticlesEvent>,
+    test_particles: Query<(), With<TestParticle>>,
 ) {
     if input.just_pressed(KeyCode::W) {
         state.world_inspector_open = !state.world_inspector_open;
     }
 
+    if input.just_pressed(KeyCode::E) {
+        state.conservation_open = !state.conservation_open;
+    }
+
+    if input.just_pressed(KeyCode::T) {
+        state.stars_open = !state.stars_open;
+    }
+
+    if input.just_pressed(KeyCode::G) {
+        state.gas_open = !state.gas_open;
+    }
+
+    if input.just_pressed(KeyCode::O) {
+        state.orbit_form_open = !state.orbit_form_open;
+    }
+
+    if input.just_pressed(KeyCode::K) {
+        state.disk_generator_open = !state.disk_generator_open;
+    }
+
     if input.just_pressed(KeyCode::P) {
         state.right_panel_open = !state.right_panel_open;
     }
@@ -91,17 +164,18 @@ fn root_ui_system(
                 state.right_panel_open = false;
             }
 
-            ui.horizontal(|ui| {
-                ui.label("[W]orld Inspector Window");
-                let txt = if state.world_inspector_open {
-                    "Close"
-                } else {
-                    "Open"
-                };
-                if ui.button(txt).clicked() {
-                    state.world_inspector_open = !state.world_inspector_open;
-                }
-            });
+            window_row(
+                ui,
+                "[W]orld Inspector Window",
+                &mut state.world_inspector_open,
+            );
+            window_row(
+                ui,
+                "[E]nergy & Momentum Window",
+                &mut state.conservation_open,
+            );
+            window_row(ui, "S[t]ars Window", &mut state.stars_open);
+            window_row(ui, "[G]as Disk Window", &mut state.gas_open);
 
             ui.separator();
 
@@ -120,7 +194,7 @@ fn root_ui_system(
                             )
                             .normalize_or_zero();
                         spawn_events.send(SpawnPlanetEvent {
-                            pos: Some(state.new_planet_pos),
+                            pos: Some(from_vec3(state.new_planet_pos)),
                             ..default()
                         });
                     }
@@ -138,6 +212,26 @@ fn root_ui_system(
                     {
                         *planet_spawn_mode = PlanetSpawnMode::EclipticPosSelect;
                     }
+
+                    if ui.button("Spawn On [O]rbit").clicked() {
+                        state.orbit_form_open = !state.orbit_form_open;
+                    }
+
+                    if ui.button("Generate Dis[k]").clicked() {
+                        state.disk_generator_open = !state.disk_generator_open;
+                    }
+                });
+
+            CollapsingHeader::new("Test Particles")
+                .default_open(true)
+                .show(ui, |ui| {
+                    ui.label(format!("Count: {}", test_particles.iter().count()));
+
+                    if ui.small_button("Spawn 10k Dust").clicked() {
+                        spawn_particle_events.send(SpawnTestParticlesEvent { count: 10_000 });
+                    }
+
+                    ui.checkbox(&mut constants.accrete_test_particles, "Accrete On Contact");
                 });
 
             CollapsingHeader::new("Constants")
@@ -158,19 +252,67 @@ fn root_ui_system(
                     });
 
                     ui.horizontal(|ui| {
-                        ui.label("Min. Attraction Dist.");
-                        ui.add(
-                            DragValue::new(&mut constants.min_attraction_dist)
-                                .speed(0.1)
-                                .clamp_range(0.0..=f32::MAX),
-                        );
+                        ui.label("Softening");
+                        egui::ComboBox::from_id_source("softening")
+                            .selected_text(constants.softening.name())
+                            .show_ui(ui, |ui| {
+                                for softening in Softening::ALL {
+                                    ui.selectable_value(
+                                        &mut constants.softening,
+                                        softening,
+                                        softening.name(),
+                                    );
+                                }
+                            });
                         egui::reset_button_with(
                             ui,
-                            &mut constants.min_attraction_dist,
-                            Constants::default().min_attraction_dist,
+                            &mut constants.softening,
+                            Constants::default().softening,
                         );
                     });
 
+                    ui.horizontal(|ui| match constants.softening {
+                        Softening::None => {
+                            ui.label("Min. Attraction Dist.");
+                            ui.add(
+                                DragValue::new(&mut constants.min_attraction_dist)
+                                    .speed(0.1)
+                                    .clamp_range(0.0..=f32::MAX),
+                            );
+                            egui::reset_button_with(
+                                ui,
+                                &mut constants.min_attraction_dist,
+                                Constants::default().min_attraction_dist,
+                            );
+                        }
+                        Softening::Plummer => {
+                            ui.label("Plummer Length ε");
+                            ui.add(
+                                DragValue::new(&mut constants.plummer_epsilon)
+                                    .speed(0.1)
+                                    .clamp_range(0.001..=f32::MAX),
+                            );
+                            egui::reset_button_with(
+                                ui,
+                                &mut constants.plummer_epsilon,
+                                Constants::default().plummer_epsilon,
+                            );
+                        }
+                        Softening::CubicSpline => {
+                            ui.label("Spline Length h");
+                            ui.add(
+                                DragValue::new(&mut constants.spline_length)
+                                    .speed(0.1)
+                                    .clamp_range(0.001..=f32::MAX),
+                            );
+                            egui::reset_button_with(
+                                ui,
+                                &mut constants.spline_length,
+                                Constants::default().spline_length,
+                            );
+                        }
+                    });
+
                     ui.horizontal(|ui| {
                         ui.label("Mouse Interaction Strength");
                         ui.add(
@@ -184,11 +326,353 @@ fn root_ui_system(
                             Constants::default().mouse_spring_strength,
                         );
                     });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Integrator");
+                        egui::ComboBox::from_id_source("integrator")
+                            .selected_text(constants.integrator.name())
+                            .show_ui(ui, |ui| {
+                                for kind in IntegratorKind::ALL {
+                                    ui.selectable_value(
+                                        &mut constants.integrator,
+                                        kind,
+                                        kind.name(),
+                                    );
+                                }
+                            });
+                        egui::reset_button_with(
+                            ui,
+                            &mut constants.integrator,
+                            Constants::default().integrator,
+                        );
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Gravity Solver");
+                        egui::ComboBox::from_id_source("gravity_solver")
+                            .selected_text(constants.gravity_solver.name())
+                            .show_ui(ui, |ui| {
+                                for solver in GravitySolver::ALL {
+                                    ui.selectable_value(
+                                        &mut constants.gravity_solver,
+                                        solver,
+                                        solver.name(),
+                                    );
+                                }
+                            });
+                        egui::reset_button_with(
+                            ui,
+                            &mut constants.gravity_solver,
+                            Constants::default().gravity_solver,
+                        );
+                    });
+
+                    if constants.gravity_solver == GravitySolver::BarnesHut {
+                        ui.horizontal(|ui| {
+                            ui.label("Opening Angle θ");
+                            ui.add(
+                                DragValue::new(&mut constants.opening_angle)
+                                    .speed(0.01)
+                                    .clamp_range(0.0..=2.0),
+                            );
+                            egui::reset_button_with(
+                                ui,
+                                &mut constants.opening_angle,
+                                Constants::default().opening_angle,
+                            );
+                        });
+
+                        ui.checkbox(&mut octree_overlay.enabled, "Show Octree");
+                    }
+
+                    ui.horizontal(|ui| {
+                        ui.label("Collisions");
+                        egui::ComboBox::from_id_source("collision_model")
+                            .selected_text(constants.collision_model.name())
+                            .show_ui(ui, |ui| {
+                                for model in CollisionModel::ALL {
+                                    ui.selectable_value(
+                                        &mut constants.collision_model,
+                                        model,
+                                        model.name(),
+                                    );
+                                }
+                            });
+                        egui::reset_button_with(
+                            ui,
+                            &mut constants.collision_model,
+                            Constants::default().collision_model,
+                        );
+                    });
+
+                    if constants.collision_model == CollisionModel::LeinhardtStewart {
+                        ui.horizontal(|ui| {
+                            ui.label("Restitution");
+                            ui.add(
+                                DragValue::new(&mut constants.restitution)
+                                    .speed(0.01)
+                                    .clamp_range(0.0..=1.0),
+                            );
+                            egui::reset_button_with(
+                                ui,
+                                &mut constants.restitution,
+                                Constants::default().restitution,
+                            );
+                        });
+
+                        ui.horizontal(|ui| {
+                            ui.label("Disruption Strength c*");
+                            ui.add(
+                                DragValue::new(&mut constants.disruption_strength)
+                                    .speed(0.1)
+                                    .clamp_range(0.01..=f32::MAX),
+                            );
+                            egui::reset_button_with(
+                                ui,
+                                &mut constants.disruption_strength,
+                                Constants::default().disruption_strength,
+                            );
+                        });
+
+                        ui.horizontal(|ui| {
+                            ui.label("Max. Fragments");
+                            ui.add(
+                                DragValue::new(&mut constants.fragment_count).clamp_range(0..=64),
+                            );
+                            egui::reset_button_with(
+                                ui,
+                                &mut constants.fragment_count,
+                                Constants::default().fragment_count,
+                            );
+                        });
+
+                        ui.horizontal(|ui| {
+                            ui.label("Min. Fragment Mass");
+                            ui.add(
+                                DragValue::new(&mut constants.min_fragment_mass)
+                                    .speed(0.1)
+                                    .clamp_range(0.01..=f32::MAX),
+                            );
+                            egui::reset_button_with(
+                                ui,
+                                &mut constants.min_fragment_mass,
+                                Constants::default().min_fragment_mass,
+                            );
+                        });
+                    }
+
+                    ui.checkbox(&mut constants.pebble_accretion, "Pebble Accretion");
+
+                    if constants.pebble_accretion {
+                        ui.horizontal(|ui| {
+                            ui.label("Pebble Flux");
+                            ui.add(
+                                DragValue::new(&mut constants.pebble_flux)
+                                    .speed(0.001)
+                                    .clamp_range(0.0..=f32::MAX),
+                            );
+                            egui::reset_button_with(
+                                ui,
+                                &mut constants.pebble_flux,
+                                Constants::default().pebble_flux,
+                            );
+                        });
+
+                        ui.horizontal(|ui| {
+                            ui.label("Pebble Stokes Number");
+                            ui.add(
+                                DragValue::new(&mut constants.pebble_stokes_number)
+                                    .speed(0.001)
+                                    .clamp_range(0.001..=10.0),
+                            );
+                            egui::reset_button_with(
+                                ui,
+                                &mut constants.pebble_stokes_number,
+                                Constants::default().pebble_stokes_number,
+                            );
+                        });
+
+                        ui.horizontal(|ui| {
+                            ui.label("Isolation Mass");
+                            ui.add(
+                                DragValue::new(&mut constants.pebble_isolation_mass)
+                                    .speed(0.1)
+                                    .clamp_range(0.0..=f32::MAX),
+                            );
+                            egui::reset_button_with(
+                                ui,
+                                &mut constants.pebble_isolation_mass,
+                                Constants::default().pebble_isolation_mass,
+                            );
+                        });
+                    }
+
+                    ui.checkbox(&mut constants.gas_accretion, "Gas Accretion");
+
+                    if constants.gas_accretion {
+                        ui.horizontal(|ui| {
+                            ui.label("Critical Core Mass");
+                            ui.add(
+                                DragValue::new(&mut constants.critical_core_mass)
+                                    .speed(0.1)
+                                    .clamp_range(0.001..=f32::MAX),
+                            );
+                            egui::reset_button_with(
+                                ui,
+                                &mut constants.critical_core_mass,
+                                Constants::default().critical_core_mass,
+                            );
+                        });
+
+                        ui.horizontal(|ui| {
+                            ui.label("Envelope Timescale");
+                            ui.add(
+                                DragValue::new(&mut constants.envelope_timescale)
+                                    .speed(1.0)
+                                    .clamp_range(0.001..=f32::MAX),
+                            );
+                            egui::reset_button_with(
+                                ui,
+                                &mut constants.envelope_timescale,
+                                Constants::default().envelope_timescale,
+                            );
+                        });
+
+                        ui.horizontal(|ui| {
+                            ui.label("Gap Opening Mass");
+                            ui.add(
+                                DragValue::new(&mut constants.gap_opening_mass)
+                                    .speed(1.0)
+                                    .clamp_range(0.0..=f32::MAX),
+                            );
+                            egui::reset_button_with(
+                                ui,
+                                &mut constants.gap_opening_mass,
+                                Constants::default().gap_opening_mass,
+                            );
+                        });
+                    }
+
+                    ui.label("Densities (g/cm³)");
+                    for material in Material::ALL {
+                        ui.horizontal(|ui| {
+                            ui.label(material.name());
+                            ui.add(
+                                DragValue::new(constants.densities.get_mut(material))
+                                    .speed(0.05)
+                                    .clamp_range(0.01..=f32::MAX),
+                            );
+                            egui::reset_button_with(
+                                ui,
+                                constants.densities.get_mut(material),
+                                Densities::default().get(material),
+                            );
+                        });
+                    }
+                });
+
+            CollapsingHeader::new("Simulation")
+                .default_open(true)
+                .show(ui, |ui| {
+                    ui.label(format!("Sim. Time: {:.2}", sim_clock.elapsed.0));
+                    ui.label(format!("Steps: {}", sim_clock.steps));
+
+                    ui.horizontal(|ui| {
+                        ui.label("Step Size");
+                        let mut step_size = sim_settings.step_size;
+                        ui.add(
+                            DragValue::new(&mut step_size)
+                                .speed(0.001)
+                                .clamp_range(0.001..=1.0),
+                        );
+                        egui::reset_button_with(
+                            ui,
+                            &mut step_size,
+                            SimSettings::default().step_size,
+                        );
+                        if step_size != sim_settings.step_size {
+                            sim_settings.step_size = step_size;
+                        }
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Substeps");
+                        let mut substeps = sim_settings.substeps;
+                        ui.add(DragValue::new(&mut substeps).clamp_range(1..=64));
+                        egui::reset_button_with(ui, &mut substeps, SimSettings::default().substeps);
+                        if substeps != sim_settings.substeps {
+                            sim_settings.substeps = substeps;
+                        }
+                    });
+
+                    ui.checkbox(&mut adaptive.enabled, "Adaptive Step");
+
+                    if adaptive.enabled {
+                        ui.horizontal(|ui| {
+                            ui.label("Error Tolerance");
+                            ui.add(
+                                DragValue::new(&mut adaptive.tolerance)
+                                    .speed(0.0001)
+                                    .clamp_range(1e-6..=1.0),
+                            );
+                            egui::reset_button_with(
+                                ui,
+                                &mut adaptive.tolerance,
+                                AdaptiveStep::default().tolerance,
+                            );
+                        });
+
+                        ui.horizontal(|ui| {
+                            ui.label("Encounter Fraction η");
+                            ui.add(
+                                DragValue::new(&mut adaptive.eta)
+                                    .speed(0.001)
+                                    .clamp_range(0.001..=1.0),
+                            );
+                            egui::reset_button_with(
+                                ui,
+                                &mut adaptive.eta,
+                                AdaptiveStep::default().eta,
+                            );
+                        });
+
+                        ui.label(format!("Current dt: {:.2e}", adaptive.dt.0));
+                        ui.label(format!("Rejected Steps: {}", adaptive.rejected_steps));
+                    }
                 });
         },
     );
 }
 
+/// A label with a button which opens or closes the window it names.
+fn window_row(ui: &mut egui::Ui, label: &str, open: &mut bool) {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        let txt = if *open { "Close" } else { "Open" };
+        if ui.button(txt).clicked() {
+            *open = !*open;
+        }
+    });
+}
+
+/// A grid row with a label and a draggable value which can be reset to
+/// `default`.
+fn drag_row(
+    ui: &mut egui::Ui,
+    label: &str,
+    value: &mut Real,
+    default: Real,
+    speed: f64,
+    range: RangeInclusive<Real>,
+) {
+    ui.label(label);
+    ui.horizontal(|ui| {
+        ui.add(DragValue::new(value).speed(speed).clamp_range(range));
+        egui::reset_button_with(ui, value, default);
+    });
+    ui.end_row();
+}
+
 #[derive(Resource, Default)]
 pub struct MouseRay(pub Option<Ray>);
 
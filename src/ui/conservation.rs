@@ -0,0 +1,120 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::{
+    bevy_egui::{egui, EguiContexts},
+    egui::plot::{Legend, Line, Plot, PlotPoints},
+};
+
+use crate::{
+    components::{to_f64, Real},
+    planet::{
+        diagnostics::{Conservation, DriftSample},
+        simulation::SimClock,
+    },
+};
+
+use super::UiState;
+
+pub struct ConservationUiPlugin;
+
+impl Plugin for ConservationUiPlugin {
+    fn build(&self, app: &mut App) {
+        app // <noformat>
+            .add_systems(Update, conservation_window_system);
+    }
+}
+
+fn conservation_window_system(
+    mut contexts: EguiContexts,
+    mut state: ResMut<UiState>,
+    mut conservation: ResMut<Conservation>,
+    clock: Res<SimClock>,
+) {
+    let mut open = state.conservation_open;
+
+    egui::Window::new("Conservation")
+        .open(&mut open)
+        .default_width(400.0)
+        .show(contexts.ctx_mut(), |ui| {
+            let totals = conservation.current;
+
+            egui::Grid::new("conservation_totals").show(ui, |ui| {
+                ui.label("Kinetic Energy");
+                ui.label(format!("{:.4e}", totals.kinetic.0));
+                ui.end_row();
+
+                ui.label("Potential Energy");
+                ui.label(format!("{:.4e}", totals.potential.0));
+                ui.end_row();
+
+                ui.label("Total Energy");
+                ui.label(format!("{:.4e}", totals.energy().0));
+                ui.end_row();
+
+                ui.label("Merge Energy Change");
+                ui.label(format!("{:.4e}", conservation.merge_energy.0));
+                ui.end_row();
+
+                ui.label("Energy From Disk");
+                ui.label(format!("{:.4e}", conservation.disk_energy.0));
+                ui.end_row();
+
+                ui.label("|Momentum|");
+                ui.label(format!("{:.4e}", totals.momentum.0.length()));
+                ui.end_row();
+
+                ui.label("|Angular Momentum|");
+                ui.label(format!("{:.4e}", totals.angular_momentum.0.length()));
+                ui.end_row();
+
+                ui.label("Merge Ang. Mom. Change");
+                ui.label(format!(
+                    "{:.4e}",
+                    conservation.merge_angular_momentum.0.length()
+                ));
+                ui.end_row();
+
+                ui.label("Ang. Mom. From Disk");
+                ui.label(format!(
+                    "{:.4e}",
+                    conservation.disk_angular_momentum.0.length()
+                ));
+                ui.end_row();
+            });
+
+            if let Some(drift) = conservation.drift(clock.elapsed.0) {
+                ui.separator();
+                ui.label(format!("Energy Drift: {:+.3e}", drift.energy));
+                ui.label(format!("Momentum Drift: {:.3e}", drift.momentum));
+                ui.label(format!(
+                    "Angular Momentum Drift: {:.3e}",
+                    drift.angular_momentum
+                ));
+            }
+
+            if ui.button("Reset Baseline").clicked() {
+                conservation.reset_baseline();
+            }
+
+            let series = |quantity: fn(&DriftSample) -> Real| {
+                conservation
+                    .history
+                    .iter()
+                    .map(|sample| [to_f64(sample.time), to_f64(quantity(sample))])
+                    .collect::<PlotPoints>()
+            };
+
+            Plot::new("conservation_drift")
+                .height(200.0)
+                .legend(Legend::default())
+                .show(ui, |plot_ui| {
+                    plot_ui.line(Line::new(series(|s| s.energy)).name("Energy"));
+                    plot_ui.line(Line::new(series(|s| s.momentum)).name("Momentum"));
+                    plot_ui
+                        .line(Line::new(series(|s| s.angular_momentum)).name("Angular Momentum"));
+                });
+        });
+
+    if open != state.conservation_open {
+        state.conservation_open = open;
+    }
+}
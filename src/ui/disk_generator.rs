@@ -0,0 +1,186 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::{
+    bevy_egui::{egui, EguiContexts},
+    egui::DragValue,
+};
+
+use crate::{
+    components::Real,
+    planet::{
+        generator::{DiskGenerator, GenerateDiskEvent, MassFunction},
+        stars::StarSystemPreset,
+    },
+};
+
+use super::{drag_row, UiState};
+
+pub struct DiskGeneratorUiPlugin;
+
+impl Plugin for DiskGeneratorUiPlugin {
+    fn build(&self, app: &mut App) {
+        app // <noformat>
+            .add_systems(Update, disk_generator_window_system);
+    }
+}
+
+fn disk_generator_window_system(
+    mut contexts: EguiContexts,
+    mut state: ResMut<UiState>,
+    mut generator: ResMut<DiskGenerator>,
+    preset: Res<StarSystemPreset>,
+    mut generate_events: EventWriter<GenerateDiskEvent>,
+) {
+    let mut open = state.disk_generator_open;
+
+    egui::Window::new("Disk Generator")
+        .open(&mut open)
+        .show(contexts.ctx_mut(), |ui| {
+            let generator = &mut *generator;
+            let defaults = DiskGenerator::default();
+
+            egui::Grid::new("disk_generator").show(ui, |ui| {
+                ui.label("Bodies");
+                ui.horizontal(|ui| {
+                    ui.add(DragValue::new(&mut generator.count).clamp_range(0..=1000));
+                    egui::reset_button_with(ui, &mut generator.count, defaults.count);
+                });
+                ui.end_row();
+
+                let outer = generator.outer_radius;
+                drag_row(
+                    ui,
+                    "Inner Radius",
+                    &mut generator.inner_radius,
+                    defaults.inner_radius,
+                    1.0,
+                    1.0..=outer,
+                );
+                let inner = generator.inner_radius;
+                drag_row(
+                    ui,
+                    "Outer Radius",
+                    &mut generator.outer_radius,
+                    defaults.outer_radius,
+                    1.0,
+                    inner..=Real::MAX,
+                );
+                drag_row(
+                    ui,
+                    "Surface Density Σ ∝ r^-p",
+                    &mut generator.surface_density_index,
+                    defaults.surface_density_index,
+                    0.01,
+                    -5.0..=5.0,
+                );
+
+                ui.label("Mass Function");
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_source("mass_function")
+                        .selected_text(generator.mass_function.name())
+                        .show_ui(ui, |ui| {
+                            for mass_function in MassFunction::ALL {
+                                ui.selectable_value(
+                                    &mut generator.mass_function,
+                                    mass_function,
+                                    mass_function.name(),
+                                );
+                            }
+                        });
+                    egui::reset_button_with(
+                        ui,
+                        &mut generator.mass_function,
+                        defaults.mass_function,
+                    );
+                });
+                ui.end_row();
+
+                let max_mass = generator.max_mass;
+                drag_row(
+                    ui,
+                    "Min Mass",
+                    &mut generator.min_mass,
+                    defaults.min_mass,
+                    0.1,
+                    0.01..=max_mass,
+                );
+                let min_mass = generator.min_mass;
+                drag_row(
+                    ui,
+                    "Max Mass",
+                    &mut generator.max_mass,
+                    defaults.max_mass,
+                    0.1,
+                    min_mass..=Real::MAX,
+                );
+                match generator.mass_function {
+                    MassFunction::PowerLaw => drag_row(
+                        ui,
+                        "dN/dM ∝ M^-α",
+                        &mut generator.mass_index,
+                        defaults.mass_index,
+                        0.01,
+                        -5.0..=5.0,
+                    ),
+                    MassFunction::LogNormal => {
+                        drag_row(
+                            ui,
+                            "Median Mass",
+                            &mut generator.median_mass,
+                            defaults.median_mass,
+                            0.1,
+                            0.01..=Real::MAX,
+                        );
+                        drag_row(
+                            ui,
+                            "σ of ln M",
+                            &mut generator.mass_sigma,
+                            defaults.mass_sigma,
+                            0.01,
+                            0.0..=5.0,
+                        );
+                    }
+                }
+
+                drag_row(
+                    ui,
+                    "Eccentricity σ",
+                    &mut generator.eccentricity_scale,
+                    defaults.eccentricity_scale,
+                    0.001,
+                    0.0..=0.5,
+                );
+                drag_row(
+                    ui,
+                    "Inclination σ (rad)",
+                    &mut generator.inclination_scale,
+                    defaults.inclination_scale,
+                    0.001,
+                    0.0..=0.5,
+                );
+                drag_row(
+                    ui,
+                    "Min Spacing (Hill radii)",
+                    &mut generator.min_spacing,
+                    defaults.min_spacing,
+                    0.01,
+                    0.0..=20.0,
+                );
+            });
+
+            if ui.button("Fit Radii To Stars").clicked() {
+                generator.fit_radii_to(*preset);
+            }
+
+            ui.horizontal(|ui| {
+                if ui.button("Add Bodies").clicked() {
+                    generate_events.send(GenerateDiskEvent { replace: false });
+                }
+
+                if ui.button("Replace Planets").clicked() {
+                    generate_events.send(GenerateDiskEvent { replace: true });
+                }
+            });
+        });
+
+    state.disk_generator_open = open;
+}
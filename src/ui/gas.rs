@@ -0,0 +1,105 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::bevy_egui::{egui, EguiContexts};
+
+use crate::{
+    components::Real,
+    planet::{gas::GasDisk, simulation::SimClock},
+};
+
+use super::{drag_row, UiState};
+
+pub struct GasUiPlugin;
+
+impl Plugin for GasUiPlugin {
+    fn build(&self, app: &mut App) {
+        app // <noformat>
+            .add_systems(Update, gas_window_system);
+    }
+}
+
+fn gas_window_system(
+    mut contexts: EguiContexts,
+    mut state: ResMut<UiState>,
+    mut gas: ResMut<GasDisk>,
+    clock: Res<SimClock>,
+) {
+    let mut open = state.gas_open;
+
+    egui::Window::new("Gas Disk")
+        .open(&mut open)
+        .show(contexts.ctx_mut(), |ui| {
+            let gas = &mut *gas;
+            let defaults = GasDisk::default();
+
+            ui.checkbox(&mut gas.enabled, "Gas Drag");
+
+            if !gas.enabled {
+                return;
+            }
+
+            ui.label(format!(
+                "Gas Remaining: {:.1}%",
+                100.0 * gas.remaining(clock.elapsed)
+            ));
+
+            egui::Grid::new("gas_disk").show(ui, |ui| {
+                drag_row(
+                    ui,
+                    "Surface Density Σ₀",
+                    &mut gas.surface_density,
+                    defaults.surface_density,
+                    1e-5,
+                    0.0..=Real::MAX,
+                );
+                drag_row(
+                    ui,
+                    "Density Index p",
+                    &mut gas.surface_density_index,
+                    defaults.surface_density_index,
+                    0.01,
+                    0.0..=3.0,
+                );
+                drag_row(
+                    ui,
+                    "Aspect Ratio H/r",
+                    &mut gas.aspect_ratio,
+                    defaults.aspect_ratio,
+                    0.001,
+                    0.001..=0.5,
+                );
+                drag_row(
+                    ui,
+                    "Dissipation Time τ",
+                    &mut gas.dissipation_time,
+                    defaults.dissipation_time,
+                    10.0,
+                    1.0..=Real::MAX,
+                );
+                drag_row(
+                    ui,
+                    "Mean Free Path",
+                    &mut gas.mean_free_path,
+                    defaults.mean_free_path,
+                    0.01,
+                    0.001..=Real::MAX,
+                );
+            });
+
+            ui.checkbox(&mut gas.migration, "Type I Migration");
+
+            if gas.migration {
+                egui::Grid::new("gas_migration").show(ui, |ui| {
+                    drag_row(
+                        ui,
+                        "Migration Efficiency",
+                        &mut gas.migration_efficiency,
+                        defaults.migration_efficiency,
+                        0.001,
+                        0.0..=1.0,
+                    );
+                });
+            }
+        });
+
+    state.gas_open = open;
+}
@@ -0,0 +1,211 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::{
+    bevy_egui::{egui, EguiContexts},
+    egui::DragValue,
+};
+
+use crate::{
+    components::{consts, to_vec3, Mass, Real},
+    planet::{disk::Star, orbit::OrbitalElements, Planet, SpawnOrbit, SpawnPlanetEvent},
+};
+
+use super::UiState;
+
+pub struct OrbitSpawningPlugin;
+
+impl Plugin for OrbitSpawningPlugin {
+    fn build(&self, app: &mut App) {
+        app // <noformat>
+            .init_resource::<OrbitForm>()
+            .add_systems(Update, (orbit_form_window_system, orbit_preview_system));
+    }
+}
+
+/// What's entered in the orbit spawning form. Angles are in degrees.
+#[derive(Resource, Clone, Copy)]
+pub struct OrbitForm {
+    /// Defaults to the first star found.
+    primary: Option<Entity>,
+    semi_major_axis: Real,
+    eccentricity: Real,
+    inclination: Real,
+    longitude_of_ascending_node: Real,
+    argument_of_periapsis: Real,
+    mean_anomaly: Real,
+    mass: Real,
+}
+
+impl Default for OrbitForm {
+    fn default() -> Self {
+        Self {
+            primary: None,
+            semi_major_axis: 200.0,
+            eccentricity: 0.1,
+            inclination: 0.0,
+            longitude_of_ascending_node: 0.0,
+            argument_of_periapsis: 0.0,
+            mean_anomaly: 0.0,
+            mass: 10.0,
+        }
+    }
+}
+
+impl OrbitForm {
+    fn elements(&self) -> OrbitalElements {
+        OrbitalElements::from_mean_anomaly(
+            self.semi_major_axis,
+            self.eccentricity,
+            self.inclination.to_radians(),
+            self.longitude_of_ascending_node.to_radians(),
+            self.argument_of_periapsis.to_radians(),
+            self.mean_anomaly.to_radians(),
+        )
+    }
+}
+
+fn orbit_form_window_system(
+    mut contexts: EguiContexts,
+    mut state: ResMut<UiState>,
+    mut form: ResMut<OrbitForm>,
+    primaries: Query<(Entity, &Name), With<Planet>>,
+    stars: Query<Entity, With<Star>>,
+    mut spawn_events: EventWriter<SpawnPlanetEvent>,
+) {
+    if form
+        .primary
+        .is_none_or(|primary| !primaries.contains(primary))
+    {
+        form.primary = stars.iter().next();
+    }
+
+    let mut open = state.orbit_form_open;
+
+    egui::Window::new("Spawn On Orbit")
+        .open(&mut open)
+        .show(contexts.ctx_mut(), |ui| {
+            let form = &mut *form;
+            let primary_name = form
+                .primary
+                .and_then(|primary| primaries.get(primary).ok())
+                .map_or("None", |(_, name)| name.as_str());
+
+            egui::Grid::new("orbit_form").show(ui, |ui| {
+                ui.label("Primary");
+                egui::ComboBox::from_id_source("orbit_primary")
+                    .selected_text(primary_name)
+                    .show_ui(ui, |ui| {
+                        for (entity, name) in &primaries {
+                            ui.selectable_value(&mut form.primary, Some(entity), name.as_str());
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("Semi-major Axis a");
+                ui.add(
+                    DragValue::new(&mut form.semi_major_axis)
+                        .speed(1.0)
+                        .clamp_range(1.0..=f32::MAX),
+                );
+                ui.end_row();
+
+                ui.label("Eccentricity e");
+                ui.add(
+                    DragValue::new(&mut form.eccentricity)
+                        .speed(0.01)
+                        .clamp_range(0.0..=0.99),
+                );
+                ui.end_row();
+
+                let angles = [
+                    ("Inclination i", &mut form.inclination, 0.0..=180.0),
+                    (
+                        "Ascending Node Ω",
+                        &mut form.longitude_of_ascending_node,
+                        0.0..=360.0,
+                    ),
+                    (
+                        "Arg. of Periapsis ω",
+                        &mut form.argument_of_periapsis,
+                        0.0..=360.0,
+                    ),
+                    ("Mean Anomaly M", &mut form.mean_anomaly, 0.0..=360.0),
+                ];
+                for (label, angle, range) in angles {
+                    ui.label(label);
+                    ui.add(
+                        DragValue::new(angle)
+                            .speed(1.0)
+                            .suffix("°")
+                            .clamp_range(range),
+                    );
+                    ui.end_row();
+                }
+
+                ui.label("Mass");
+                ui.add(
+                    DragValue::new(&mut form.mass)
+                        .speed(0.1)
+                        .clamp_range(0.01..=f32::MAX),
+                );
+                ui.end_row();
+            });
+
+            ui.horizontal(|ui| {
+                if ui.button("Spawn").clicked() {
+                    if let Some(primary) = form.primary {
+                        spawn_events.send(SpawnPlanetEvent {
+                            mass: Some(Mass(form.mass)),
+                            orbit: Some(SpawnOrbit {
+                                primary,
+                                elements: form.elements(),
+                            }),
+                            ..default()
+                        });
+                    }
+                }
+
+                if ui.button("Reset").clicked() {
+                    *form = OrbitForm {
+                        primary: form.primary,
+                        ..default()
+                    };
+                }
+            });
+        });
+
+    state.orbit_form_open = open;
+}
+
+/// Points the preview ellipse is drawn through.
+const PREVIEW_SEGMENTS: usize = 128;
+
+/// Traces the orbit the form describes around its primary, and marks where
+/// the planet would start.
+fn orbit_preview_system(
+    state: Res<UiState>,
+    form: Res<OrbitForm>,
+    primaries: Query<&GlobalTransform, With<Planet>>,
+    mut gizmos: Gizmos,
+) {
+    if !state.orbit_form_open {
+        return;
+    }
+
+    let Some(center) = form
+        .primary
+        .and_then(|primary| primaries.get(primary).ok())
+        .map(GlobalTransform::translation)
+    else {
+        return;
+    };
+
+    let elements = form.elements();
+    let ellipse = (0..=PREVIEW_SEGMENTS).map(|k| {
+        let true_anomaly = consts::TAU * k as Real / PREVIEW_SEGMENTS as Real;
+        center + to_vec3(elements.position_at(true_anomaly))
+    });
+    gizmos.linestrip(ellipse, Color::CYAN);
+
+    let start = center + to_vec3(elements.position_at(elements.true_anomaly));
+    gizmos.sphere(start, Quat::IDENTITY, 3.0, Color::CYAN);
+}
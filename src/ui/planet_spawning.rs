@@ -3,8 +3,11 @@ use std::f32::consts::{SQRT_2, TAU};
 use bevy::prelude::*;
 
 use crate::{
-    components::Radius,
-    planet::{mass_from_radius, SpawnPlanetEvent, Sun},
+    components::{from_f32, from_vec3, to_vec3, Mass, Position, Radius, Velocity},
+    planet::{
+        composition::Composition, disk::Star, mass_from_radius, stars::orbit_center, Constants,
+        SpawnPlanetEvent,
+    },
     MainCamera,
 };
 
@@ -53,14 +56,23 @@ impl PlanetSpawnMode {
     }
 }
 
+type StarsData<'a, 'b, 'c> = (&'a Position, &'b Velocity, &'c Mass);
+
+/// Where the star or barycenter a planet at `pos` would orbit is.
+fn orbit_center_tsl(stars: &Query<StarsData, With<Star>>, pos: Vec3) -> Vec3 {
+    orbit_center(stars, from_vec3(pos)).map_or(Vec3::ZERO, |center| to_vec3(center.pos))
+}
+
+#[allow(clippy::too_many_arguments)]
 fn planet_spawn_interaction_system(
-    q_sun: Query<&Transform, With<Sun>>,
+    stars: Query<StarsData, With<Star>>,
     mouse_ray: Res<MouseRay>,
     mut state: ResMut<PlanetSpawnMode>,
     mut gizmos: Gizmos,
     input: Res<Input<MouseButton>>,
     q_cam: Query<&Transform, With<MainCamera>>,
     mut spawn_planet: EventWriter<SpawnPlanetEvent>,
+    constants: Res<Constants>,
 ) {
     use PlanetSpawnMode as Mode;
 
@@ -68,17 +80,17 @@ fn planet_spawn_interaction_system(
         Mode::Nothing => (),
 
         Mode::EclipticPosSelect => {
-            let sun_tsl = q_sun.single().translation;
             let Some(mouse_tsl) = mouse_ray.intersect_plane(Vec3::ZERO, Vec3::Y) else {
             return;
         };
-            let line_len = (sun_tsl - mouse_tsl).length();
-            gizmos.line(sun_tsl, mouse_tsl, Color::CYAN);
+            let center_tsl = orbit_center_tsl(&stars, mouse_tsl);
+            let line_len = (center_tsl - mouse_tsl).length();
+            gizmos.line(center_tsl, mouse_tsl, Color::CYAN);
             gizmos.rect(
-                sun_tsl,
+                center_tsl,
                 Quat::from_rotation_arc(
                     Vec3::new(1.0, 0.0, 1.0).normalize(),
-                    mouse_tsl.normalize_or_zero(),
+                    (mouse_tsl - center_tsl).normalize_or_zero(),
                 )
                 .mul_quat(Quat::from_axis_angle(Vec3::X, TAU / 4.0)),
                 Vec2::splat(SQRT_2 * line_len),
@@ -95,23 +107,23 @@ fn planet_spawn_interaction_system(
         &Mode::HeightSelect {
             chosen_ecliptic_pos,
         } => {
-            let sun_tsl = q_sun.single().translation;
+            let center_tsl = orbit_center_tsl(&stars, chosen_ecliptic_pos);
             let cam = q_cam.single();
             let Some(mouse_tsl) = mouse_ray.intersect_plane(chosen_ecliptic_pos, cam.forward()) else {
             return;
         };
 
             let chosen_pos = chosen_ecliptic_pos + mouse_tsl.project_onto(Vec3::Y);
-            gizmos.line(sun_tsl, chosen_ecliptic_pos, Color::GOLD);
-            gizmos.line(sun_tsl, chosen_pos, Color::CYAN);
+            gizmos.line(center_tsl, chosen_ecliptic_pos, Color::GOLD);
+            gizmos.line(center_tsl, chosen_pos, Color::CYAN);
             gizmos.line(chosen_ecliptic_pos, chosen_pos, Color::CYAN);
 
-            let line_len = (sun_tsl - chosen_ecliptic_pos).length();
+            let line_len = (center_tsl - chosen_ecliptic_pos).length();
             gizmos.rect(
-                sun_tsl,
+                center_tsl,
                 Quat::from_rotation_arc(
                     Vec3::new(1.0, 0.0, 1.0).normalize(),
-                    chosen_ecliptic_pos.normalize_or_zero(),
+                    (chosen_ecliptic_pos - center_tsl).normalize_or_zero(),
                 )
                 .mul_quat(Quat::from_axis_angle(Vec3::X, TAU / 4.0)),
                 Vec2::splat(SQRT_2 * line_len),
@@ -130,36 +142,39 @@ fn planet_spawn_interaction_system(
             chosen_ecliptic_pos,
             chosen_pos,
         } => {
-            let sun_tsl = q_sun.single().translation;
+            let center_tsl = orbit_center_tsl(&stars, chosen_ecliptic_pos);
             let cam = q_cam.single();
             let Some(mouse_tsl) = mouse_ray.intersect_plane(chosen_pos, cam.forward()) else {
             return;
         };
 
-            gizmos.line(sun_tsl, chosen_ecliptic_pos, Color::GOLD);
-            gizmos.line(sun_tsl, chosen_pos, Color::GOLD);
+            gizmos.line(center_tsl, chosen_ecliptic_pos, Color::GOLD);
+            gizmos.line(center_tsl, chosen_pos, Color::GOLD);
             gizmos.line(chosen_ecliptic_pos, chosen_pos, Color::GOLD);
 
-            let line_len = (sun_tsl - chosen_ecliptic_pos).length();
+            let line_len = (center_tsl - chosen_ecliptic_pos).length();
             gizmos.rect(
-                sun_tsl,
+                center_tsl,
                 Quat::from_rotation_arc(
                     Vec3::new(1.0, 0.0, 1.0).normalize(),
-                    chosen_ecliptic_pos.normalize_or_zero(),
+                    (chosen_ecliptic_pos - center_tsl).normalize_or_zero(),
                 )
                 .mul_quat(Quat::from_axis_angle(Vec3::X, TAU / 4.0)),
                 Vec2::splat(SQRT_2 * line_len),
                 Color::GOLD,
             );
 
-            let radius = Radius(2.5 * (mouse_tsl - chosen_pos).length().sqrt());
+            let radius = 2.5 * (mouse_tsl - chosen_pos).length().sqrt();
 
-            gizmos.sphere(chosen_pos, Quat::IDENTITY, radius.0, Color::CYAN);
+            gizmos.sphere(chosen_pos, Quat::IDENTITY, radius, Color::CYAN);
 
             if input.just_released(MouseButton::Left) {
+                let composition = Composition::EARTH_LIKE;
+                let density = composition.density(&constants.densities);
                 spawn_planet.send(SpawnPlanetEvent {
-                    pos: Some(chosen_pos),
-                    mass: Some(mass_from_radius(radius)),
+                    pos: Some(from_vec3(chosen_pos)),
+                    mass: Some(mass_from_radius(Radius(from_f32(radius)), density)),
+                    composition: Some(composition),
                     ..default()
                 });
                 *state = Mode::Nothing;
@@ -0,0 +1,173 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::bevy_egui::{egui, EguiContexts};
+
+use crate::{
+    components::{to_f32, Mass, Radius, Velocity},
+    planet::{composition::Composition, gas::Migration, orbit::OrbitalElements, Planet},
+};
+
+use super::{planet_spawning::PlanetSpawnMode, MouseRay};
+
+pub struct SelectionPlugin;
+
+impl Plugin for SelectionPlugin {
+    fn build(&self, app: &mut App) {
+        app // <noformat>
+            .init_resource::<SelectedPlanet>()
+            .add_systems(
+                Update,
+                (
+                    select_planet_system,
+                    selected_planet_window_system,
+                    selection_highlight_system,
+                ),
+            );
+    }
+}
+
+/// The planet whose info is being shown, picked by clicking on it.
+#[derive(Resource, Default)]
+pub struct SelectedPlanet(pub Option<Entity>);
+
+/// Planets this small on screen are picked as though they were this many
+/// radians across, so that far-off ones can still be clicked.
+const MIN_PICK_ANGLE: f32 = 0.01;
+
+fn select_planet_system(
+    mut contexts: EguiContexts,
+    mouse_ray: Res<MouseRay>,
+    input: Res<Input<MouseButton>>,
+    planet_spawn_mode: Res<PlanetSpawnMode>,
+    planets: Query<(Entity, &GlobalTransform, &Radius), With<Planet>>,
+    mut selected: ResMut<SelectedPlanet>,
+) {
+    if !input.just_pressed(MouseButton::Left)
+        || !planet_spawn_mode.is_nothing()
+        || contexts.ctx_mut().is_pointer_over_area()
+    {
+        return;
+    }
+
+    let Some(ray) = mouse_ray.0 else {
+        return;
+    };
+
+    // The nearest planet along the ray which the ray passes through. Missing
+    // every planet keeps the selection, since the same button orbits the camera.
+    let hit = planets
+        .iter()
+        .filter_map(|(entity, tsf, radius)| {
+            let to_center = tsf.translation() - ray.origin;
+            let along = to_center.dot(ray.direction);
+            let miss_sq = to_center.length_squared() - along * along;
+            let pick_radius = to_f32(radius.0).max(MIN_PICK_ANGLE * along);
+            (along > 0.0 && miss_sq <= pick_radius * pick_radius).then_some((entity, along))
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(entity, _)| entity);
+
+    if hit.is_some() {
+        selected.0 = hit;
+    }
+}
+
+type SelectedPlanetData<'a, 'b, 'c, 'd, 'e, 'f, 'g> = (
+    &'a Name,
+    &'b Mass,
+    &'c Radius,
+    &'d Velocity,
+    &'e Composition,
+    Option<&'f Migration>,
+    Option<&'g OrbitalElements>,
+);
+
+fn selected_planet_window_system(
+    mut contexts: EguiContexts,
+    mut selected: ResMut<SelectedPlanet>,
+    planets: Query<SelectedPlanetData>,
+) {
+    let Some(entity) = selected.0 else {
+        return;
+    };
+
+    // The planet may have been merged away since it was picked.
+    let Ok((name, mass, radius, vel, composition, migration, orbit)) = planets.get(entity) else {
+        selected.0 = None;
+        return;
+    };
+
+    let mut open = true;
+
+    egui::Window::new("Selected Planet")
+        .open(&mut open)
+        .show(contexts.ctx_mut(), |ui| {
+            egui::Grid::new("selected_planet").show(ui, |ui| {
+                ui.label("Name");
+                ui.label(name.as_str());
+                ui.end_row();
+
+                ui.label("Mass");
+                ui.label(format!("{:.3}", mass.0));
+                ui.end_row();
+
+                ui.label("Radius");
+                ui.label(format!("{:.3}", radius.0));
+                ui.end_row();
+
+                ui.label("Speed");
+                ui.label(format!("{:.3}", vel.0.length()));
+                ui.end_row();
+
+                ui.label("Composition");
+                ui.label(format!(
+                    "{:.0}% rock, {:.0}% metal, {:.0}% ice, {:.0}% gas",
+                    100.0 * composition.rock,
+                    100.0 * composition.metal,
+                    100.0 * composition.ice,
+                    100.0 * composition.gas,
+                ));
+                ui.end_row();
+
+                if let Some(orbit) = orbit {
+                    ui.label("Semi-major Axis");
+                    ui.label(format!("{:.3}", orbit.semi_major_axis));
+                    ui.end_row();
+
+                    ui.label("Eccentricity");
+                    ui.label(format!("{:.4}", orbit.eccentricity));
+                    ui.end_row();
+
+                    ui.label("Inclination");
+                    ui.label(format!("{:.2}°", orbit.inclination.to_degrees()));
+                    ui.end_row();
+                }
+
+                if let Some(migration) = migration {
+                    ui.label("|Migration Force|");
+                    ui.label(format!("{:.4e}", migration.force.0.length()));
+                    ui.end_row();
+                }
+            });
+        });
+
+    if !open {
+        selected.0 = None;
+    }
+}
+
+fn selection_highlight_system(
+    selected: Res<SelectedPlanet>,
+    planets: Query<(&GlobalTransform, &Radius)>,
+    mut gizmos: Gizmos,
+) {
+    let Some((tsf, radius)) = selected.0.and_then(|entity| planets.get(entity).ok()) else {
+        return;
+    };
+
+    gizmos.sphere(
+        tsf.translation(),
+        Quat::IDENTITY,
+        1.2 * to_f32(radius.0),
+        Color::WHITE,
+    );
+}
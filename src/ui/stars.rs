@@ -0,0 +1,83 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::bevy_egui::{egui, EguiContexts};
+
+use crate::{
+    components::Real,
+    planet::{
+        disk::{SnowLineOverlay, Star, AU},
+        stars::{SpawnStarSystemEvent, StarSystemPreset, StellarAccretion},
+    },
+};
+
+use super::{drag_row, UiState};
+
+pub struct StarsUiPlugin;
+
+impl Plugin for StarsUiPlugin {
+    fn build(&self, app: &mut App) {
+        app // <noformat>
+            .add_systems(Update, stars_window_system);
+    }
+}
+
+fn stars_window_system(
+    mut contexts: EguiContexts,
+    mut state: ResMut<UiState>,
+    mut stars: Query<(&Name, &mut Star, &StellarAccretion)>,
+    mut snow_line_overlay: ResMut<SnowLineOverlay>,
+    mut spawn_star_system_events: EventWriter<SpawnStarSystemEvent>,
+) {
+    let mut open = state.stars_open;
+
+    egui::Window::new("Stars")
+        .open(&mut open)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_source("star_system_preset")
+                    .selected_text(state.star_system_preset.name())
+                    .show_ui(ui, |ui| {
+                        for preset in StarSystemPreset::ALL {
+                            ui.selectable_value(
+                                &mut state.star_system_preset,
+                                preset,
+                                preset.name(),
+                            );
+                        }
+                    });
+
+                if ui.button("Replace Stars").clicked() {
+                    spawn_star_system_events.send(SpawnStarSystemEvent {
+                        preset: state.star_system_preset,
+                    });
+                }
+            });
+
+            egui::Grid::new("stars").show(ui, |ui| {
+                for (name, mut star, accretion) in &mut stars {
+                    drag_row(
+                        ui,
+                        &format!("{name} Luminosity (L☉)"),
+                        &mut star.luminosity,
+                        Star::default().luminosity,
+                        0.01,
+                        0.0..=Real::MAX,
+                    );
+
+                    ui.label("Snow Line");
+                    ui.label(format!("{:.2} AU", star.snow_line() / AU));
+                    ui.end_row();
+
+                    ui.label("Accreted");
+                    ui.label(format!(
+                        "{:.2} from {} bodies",
+                        accretion.mass.0, accretion.bodies
+                    ));
+                    ui.end_row();
+                }
+            });
+
+            ui.checkbox(&mut snow_line_overlay.enabled, "Show Snow Line");
+        });
+
+    state.stars_open = open;
+}